@@ -0,0 +1,457 @@
+// Assembler: Cowgod's mnemonics, as printed by the disassembler, to a ROM loaded at 0x200.
+//
+// A line is an optional label, an instruction or a directive, and an optional comment:
+//     loop:   LD V0, 0x05         ; comments start with a semicolon
+// Directives:
+//     NAME EQU expression         a constant
+//     DB expression, ...          bytes
+//     DW expression, ...          big endian words
+// Expressions add or subtract numbers, labels and constants: sprite + 5.
+// Numbers are decimal, hexadecimal (0x1F or $1F) or binary (0b101 or %101). Sprites can be drawn
+// with binary numbers, where . is a 0 and # a 1: DB %..####..
+// Mnemonics, registers and directives are case insensitive, labels and constants aren't.
+use crate::instruction::{encode, Instruction};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+// where ROMs are loaded
+const START: usize = 0x200;
+// how deep constants can refer to other constants
+const MAX_DEPTH: usize = 32;
+
+const MNEMONICS: [&str; 33] = [
+    "CLS", "RET", "SCD", "SCU", "SCR", "SCL", "EXIT", "LOW", "HIGH", "JP", "CALL", "SE", "SNE",
+    "SAVE", "LOAD", "LD", "ADD", "OR", "AND", "XOR", "SUB", "SHR", "SUBN", "SHL", "RND", "DRW",
+    "SKP", "SKNP", "PLANE", "AUDIO", "PITCH", "DB", "DW",
+];
+
+// An error in the source, lines and columns start at 1
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+// A piece of a line and where it starts
+#[derive(Clone, Copy, Debug)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Token<'a> {
+    fn error(&self, message: String) -> AsmError {
+        AsmError {
+            line: self.line,
+            column: self.column,
+            message,
+        }
+    }
+
+    // the part of the token from byte start to byte end
+    fn slice(&self, start: usize, end: usize) -> Token<'a> {
+        Token {
+            text: &self.text[start..end],
+            line: self.line,
+            column: self.column + self.text[..start].chars().count(),
+        }
+    }
+
+    fn trim(&self) -> Token<'a> {
+        let start = self.text.len() - self.text.trim_start().len();
+        let end = self.text.trim_end().len().max(start);
+        self.slice(start, end)
+    }
+
+    // the first word and the rest, trimmed
+    fn split_word(&self) -> (Token<'a>, Token<'a>) {
+        let token = self.trim();
+        let end = token
+            .text
+            .find(char::is_whitespace)
+            .unwrap_or(token.text.len());
+        let len = token.text.len();
+        (token.slice(0, end), token.slice(end, len).trim())
+    }
+
+    // the comma separated operands
+    fn split_operands(&self) -> Vec<Token<'a>> {
+        if self.text.trim().is_empty() {
+            return Vec::new();
+        }
+        let mut operands = Vec::new();
+        let mut start = 0;
+        for (index, _) in self.text.match_indices(',') {
+            operands.push(self.slice(start, index).trim());
+            start = index + 1;
+        }
+        operands.push(self.slice(start, self.text.len()).trim());
+        operands
+    }
+
+    fn is(&self, keyword: &str) -> bool {
+        self.text.eq_ignore_ascii_case(keyword)
+    }
+}
+
+// An instruction or a data directive, placed at address
+struct Statement<'a> {
+    address: usize,
+    mnemonic: Token<'a>,
+    operands: Vec<Token<'a>>,
+}
+
+enum Symbol<'a> {
+    Label(usize),
+    // evaluated where it's used, it can refer to labels defined later
+    Constant(Token<'a>),
+}
+
+// Assemble a program, or list all the errors in it
+pub fn assemble(source: &str) -> Result<Vec<u8>, Vec<AsmError>> {
+    let mut errors = Vec::new();
+    let mut symbols = HashMap::new();
+    let mut statements = Vec::new();
+
+    // first pass: the address of every label
+    let mut address = START;
+    for (index, line) in source.lines().enumerate() {
+        let code = line.split(';').next().unwrap_or("");
+        let mut rest = Token {
+            text: code,
+            line: index + 1,
+            column: 1,
+        }
+        .trim();
+        loop {
+            let (word, after) = rest.split_word();
+            let name = match word.text.strip_suffix(':') {
+                Some(name) => word.slice(0, name.len()),
+                None => break,
+            };
+            if let Err(e) = define(&mut symbols, name, Symbol::Label(address)) {
+                errors.push(e);
+            }
+            rest = after;
+        }
+        if rest.text.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, after) = rest.split_word();
+        let (directive, value) = after.split_word();
+        if directive.is("EQU") {
+            if let Err(e) = define(&mut symbols, mnemonic, Symbol::Constant(value)) {
+                errors.push(e);
+            }
+            continue;
+        }
+        let operands = after.split_operands();
+        let size = if mnemonic.is("DB") {
+            operands.len()
+        } else if mnemonic.is("DW") {
+            operands.len() * 2
+        } else if mnemonic.is("LD") && operands.get(1).is_some_and(|op| long(op).is_some()) {
+            4
+        } else {
+            2
+        };
+        statements.push(Statement {
+            address,
+            mnemonic,
+            operands,
+        });
+        address += size;
+    }
+
+    // second pass: the bytes
+    let mut rom = Vec::with_capacity(address - START);
+    for statement in statements.iter() {
+        match assemble_statement(statement, &symbols) {
+            Ok(bytes) => {
+                // statements after an error are still checked, but their bytes are misplaced
+                debug_assert!(!errors.is_empty() || rom.len() == statement.address - START);
+                rom.extend_from_slice(&bytes);
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+    if address > 0x10000 {
+        errors.push(AsmError {
+            line: source.lines().count(),
+            column: 1,
+            message: format!("The program is too large ({} bytes)", address - START),
+        });
+    }
+
+    if errors.is_empty() {
+        Ok(rom)
+    } else {
+        errors.sort_by_key(|e| (e.line, e.column));
+        Err(errors)
+    }
+}
+
+fn define<'a>(
+    symbols: &mut HashMap<&'a str, Symbol<'a>>,
+    name: Token<'a>,
+    symbol: Symbol<'a>,
+) -> Result<(), AsmError> {
+    let mut chars = name.text.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+    if !valid {
+        return Err(name.error(format!("Invalid name: {}", name.text)));
+    }
+    if register(&name).is_some() || MNEMONICS.iter().any(|&mnemonic| name.is(mnemonic)) {
+        return Err(name.error(format!("Reserved name: {}", name.text)));
+    }
+    if symbols.insert(name.text, symbol).is_some() {
+        return Err(name.error(format!("{} is already defined", name.text)));
+    }
+    Ok(())
+}
+
+fn assemble_statement(
+    statement: &Statement,
+    symbols: &HashMap<&str, Symbol>,
+) -> Result<Vec<u8>, AsmError> {
+    let ops = &statement.operands;
+    let name = statement.mnemonic.text.to_ascii_uppercase();
+    let value = |index: usize, max: u16| evaluate_max(&ops[index], symbols, max);
+    let vx = |index: usize| {
+        let op = &ops[index];
+        register(op).ok_or_else(|| op.error(format!("Expected a register, found {}", op.text)))
+    };
+    let byte = |index: usize| value(index, 0xFF).map(|value| value as u8);
+    let addr = |index: usize| value(index, 0xFFF);
+
+    let instruction = match (name.as_str(), ops.len()) {
+        ("DB", len) if len > 0 => {
+            return (0..len).map(byte).collect();
+        }
+        ("DW", len) if len > 0 => {
+            let mut bytes = Vec::with_capacity(len * 2);
+            for index in 0..len {
+                bytes.extend_from_slice(&value(index, 0xFFFF)?.to_be_bytes());
+            }
+            return Ok(bytes);
+        }
+        ("CLS", 0) => Instruction::Cls,
+        ("RET", 0) => Instruction::Ret,
+        ("SCD", 1) => Instruction::ScrollDown(value(0, 0xF)? as u8),
+        ("SCU", 1) => Instruction::ScrollUp(value(0, 0xF)? as u8),
+        ("SCR", 0) => Instruction::ScrollRight,
+        ("SCL", 0) => Instruction::ScrollLeft,
+        ("EXIT", 0) => Instruction::Exit,
+        ("LOW", 0) => Instruction::Lores,
+        ("HIGH", 0) => Instruction::Hires,
+        ("JP", 1) => Instruction::Jump(addr(0)?),
+        ("JP", 2) if ops[0].is("V0") => Instruction::JumpOffset(0, addr(1)?),
+        ("CALL", 1) => Instruction::Call(addr(0)?),
+        ("SE", 2) => match register(&ops[1]) {
+            Some(y) => Instruction::SkipEq(vx(0)?, y),
+            None => Instruction::SkipEqImm(vx(0)?, byte(1)?),
+        },
+        ("SNE", 2) => match register(&ops[1]) {
+            Some(y) => Instruction::SkipNe(vx(0)?, y),
+            None => Instruction::SkipNeImm(vx(0)?, byte(1)?),
+        },
+        ("SAVE", 1) => {
+            let (x, y) = range(&ops[0])?;
+            Instruction::StoreRange(x, y)
+        }
+        ("LOAD", 1) => {
+            let (x, y) = range(&ops[0])?;
+            Instruction::LoadRange(x, y)
+        }
+        ("LD", 2) => {
+            if let Some(operand) = long(&ops[1]).filter(|_| ops[0].is("I")) {
+                let value = evaluate_max(&operand, symbols, 0xFFFF)?;
+                let mut bytes = encode(Instruction::LoadILong).to_be_bytes().to_vec();
+                bytes.extend_from_slice(&value.to_be_bytes());
+                return Ok(bytes);
+            }
+            load(ops, symbols)?
+        }
+        ("ADD", 2) if ops[0].is("I") => Instruction::AddI(vx(1)?),
+        ("ADD", 2) => match register(&ops[1]) {
+            Some(y) => Instruction::Add(vx(0)?, y),
+            None => Instruction::AddImm(vx(0)?, byte(1)?),
+        },
+        ("OR", 2) => Instruction::Or(vx(0)?, vx(1)?),
+        ("AND", 2) => Instruction::And(vx(0)?, vx(1)?),
+        ("XOR", 2) => Instruction::Xor(vx(0)?, vx(1)?),
+        ("SUB", 2) => Instruction::Sub(vx(0)?, vx(1)?),
+        ("SUBN", 2) => Instruction::SubN(vx(0)?, vx(1)?),
+        // without vy, vx is shifted in place whatever the shift quirk
+        ("SHR", 1) => Instruction::ShiftRight(vx(0)?, vx(0)?),
+        ("SHR", 2) => Instruction::ShiftRight(vx(0)?, vx(1)?),
+        ("SHL", 1) => Instruction::ShiftLeft(vx(0)?, vx(0)?),
+        ("SHL", 2) => Instruction::ShiftLeft(vx(0)?, vx(1)?),
+        ("RND", 2) => Instruction::Random(vx(0)?, byte(1)?),
+        ("DRW", 3) => Instruction::Draw(vx(0)?, vx(1)?, value(2, 0xF)? as u8),
+        ("SKP", 1) => Instruction::SkipKey(vx(0)?),
+        ("SKNP", 1) => Instruction::SkipNotKey(vx(0)?),
+        ("PLANE", 1) => Instruction::Plane(value(0, 0x3)? as u8),
+        ("AUDIO", 0) => Instruction::Audio,
+        ("PITCH", 1) => Instruction::Pitch(vx(0)?),
+        _ => {
+            let mnemonic = &statement.mnemonic;
+            if MNEMONICS.contains(&name.as_str()) {
+                return Err(mnemonic.error(format!("Invalid operands for {}", mnemonic.text)));
+            }
+            return Err(mnemonic.error(format!("Unknown instruction: {}", mnemonic.text)));
+        }
+    };
+    Ok(encode(instruction).to_be_bytes().to_vec())
+}
+
+// the many forms of LD, but LD I, long
+fn load(ops: &[Token], symbols: &HashMap<&str, Symbol>) -> Result<Instruction, AsmError> {
+    let (target, source) = (&ops[0], &ops[1]);
+    let vx = |op: &Token| {
+        register(op).ok_or_else(|| op.error(format!("Expected a register, found {}", op.text)))
+    };
+    if let Some(x) = register(target) {
+        let instruction = match register(source) {
+            Some(y) => Instruction::Move(x, y),
+            None if source.is("DT") => Instruction::GetDelay(x),
+            None if source.is("K") => Instruction::WaitKey(x),
+            None if source.is("[I]") => Instruction::LoadRegisters(x),
+            None if source.is("R") => Instruction::LoadFlags(x),
+            None => Instruction::LoadImm(x, evaluate_max(source, symbols, 0xFF)? as u8),
+        };
+        return Ok(instruction);
+    }
+    let instruction = match target.text.to_ascii_uppercase().as_str() {
+        "I" => Instruction::LoadI(evaluate_max(source, symbols, 0xFFF)?),
+        "DT" => Instruction::SetDelay(vx(source)?),
+        "ST" => Instruction::SetSound(vx(source)?),
+        "F" => Instruction::Font(vx(source)?),
+        "HF" => Instruction::BigFont(vx(source)?),
+        "B" => Instruction::Bcd(vx(source)?),
+        "[I]" => Instruction::StoreRegisters(vx(source)?),
+        "R" => Instruction::StoreFlags(vx(source)?),
+        _ => return Err(target.error(format!("Can't load into {}", target.text))),
+    };
+    Ok(instruction)
+}
+
+// the address of LD I, long address
+fn long<'a>(op: &Token<'a>) -> Option<Token<'a>> {
+    let (word, rest) = op.split_word();
+    if word.is("long") && !rest.text.is_empty() {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+// v0 - vf
+fn register(op: &Token) -> Option<u8> {
+    let mut chars = op.text.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('v'), Some(x), None) | (Some('V'), Some(x), None) => x.to_digit(16).map(|x| x as u8),
+        _ => None,
+    }
+}
+
+// vx - vy, the operand of SAVE and LOAD
+fn range(op: &Token) -> Result<(u8, u8), AsmError> {
+    let error = || {
+        op.error(format!(
+            "Expected a register range (vx - vy), found {}",
+            op.text
+        ))
+    };
+    let dash = op.text.find('-').ok_or_else(error)?;
+    let first = op.slice(0, dash).trim();
+    let second = op.slice(dash + 1, op.text.len()).trim();
+    match (register(&first), register(&second)) {
+        (Some(x), Some(y)) => Ok((x, y)),
+        _ => Err(error()),
+    }
+}
+
+fn evaluate_max(op: &Token, symbols: &HashMap<&str, Symbol>, max: u16) -> Result<u16, AsmError> {
+    let value = evaluate(op, symbols, 0)?;
+    if value < 0 || value > max as i64 {
+        return Err(op.error(format!("{} doesn't fit in 0 - {:#X}", value, max)));
+    }
+    Ok(value as u16)
+}
+
+// the terms of the expression added or subtracted, left to right
+fn evaluate(op: &Token, symbols: &HashMap<&str, Symbol>, depth: usize) -> Result<i64, AsmError> {
+    let mut total = 0;
+    let mut sign = 1;
+    let mut start = 0;
+    let ends = op.text.match_indices(['+', '-']);
+    for (end, operator) in ends.chain(std::iter::once((op.text.len(), ""))) {
+        let term = op.slice(start, end).trim();
+        total += sign * term_value(&term, symbols, depth)?;
+        sign = if operator == "-" { -1 } else { 1 };
+        start = end + operator.len();
+    }
+    Ok(total)
+}
+
+fn term_value(
+    term: &Token,
+    symbols: &HashMap<&str, Symbol>,
+    depth: usize,
+) -> Result<i64, AsmError> {
+    let text = term.text;
+    if text.is_empty() {
+        return Err(term.error("Missing value".to_string()));
+    }
+    if text.starts_with(|c: char| c.is_ascii_digit() || c == '$' || c == '%') {
+        return number(text).ok_or_else(|| term.error(format!("Invalid number: {}", text)));
+    }
+    match symbols.get(text) {
+        Some(Symbol::Label(address)) => Ok(*address as i64),
+        Some(Symbol::Constant(_)) if depth == MAX_DEPTH => {
+            Err(term.error(format!("{} is defined in terms of itself", text)))
+        }
+        Some(Symbol::Constant(expression)) => evaluate(expression, symbols, depth + 1),
+        None => Err(term.error(format!("Undefined name: {}", text))),
+    }
+}
+
+fn number(text: &str) -> Option<i64> {
+    let hex = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X"));
+    if let Some(hex) = hex.or_else(|| text.strip_prefix('$')) {
+        return i64::from_str_radix(hex, 16).ok();
+    }
+    let binary = text.strip_prefix("0b").or_else(|| text.strip_prefix("0B"));
+    if let Some(binary) = binary.or_else(|| text.strip_prefix('%')) {
+        let binary: String = binary
+            .chars()
+            .map(|c| match c {
+                '.' => '0',
+                '#' => '1',
+                _ => c,
+            })
+            .collect();
+        return i64::from_str_radix(&binary, 2).ok();
+    }
+    text.parse().ok()
+}
+
+#[cfg(test)]
+#[path = "./asm_tests.rs"]
+mod asm_tests;
@@ -0,0 +1,94 @@
+use super::*;
+use crate::disasm::{disassemble_rom, Syntax};
+use crate::Mode;
+use std::fs;
+
+fn errors(source: &str) -> Vec<(usize, usize)> {
+    assemble(source)
+        .unwrap_err()
+        .iter()
+        .map(|e| (e.line, e.column))
+        .collect()
+}
+
+#[test]
+fn assemble_instructions() {
+    let source = "
+        CLS
+        ld va, 0x12       ; a comment
+        DRW V0, V1, 5
+        LD [I], V3
+        ADD VA, VB
+        SHR V4
+        SAVE V2 - V5
+        LD I, long $1234
+        PLANE 3
+    ";
+    assert_eq!(
+        assemble(source).unwrap(),
+        [
+            0x00, 0xE0, 0x6A, 0x12, 0xD0, 0x15, 0xF3, 0x55, 0x8A, 0xB4, 0x84, 0x46, 0x52, 0x52,
+            0xF0, 0x00, 0x12, 0x34, 0xF3, 0x01,
+        ]
+    );
+}
+
+#[test]
+fn assemble_labels_and_constants() {
+    let source = "
+SPEED EQU STEP + 1
+STEP EQU 2
+start:  LD I, sprite
+        CALL routine
+        JP start
+routine: ADD V0, SPEED
+        RET
+sprite: DB %#..##..#, 0b00001111
+        DW sprite - 2, 0xABCD
+    ";
+    assert_eq!(
+        assemble(source).unwrap(),
+        [
+            0xA2, 0x0A, // 0x200: LD I, sprite
+            0x22, 0x06, // 0x202: CALL routine
+            0x12, 0x00, // 0x204: JP start
+            0x70, 0x03, // 0x206: ADD V0, 3
+            0x00, 0xEE, // 0x208: RET
+            0x99, 0x0F, // 0x20A: the sprite
+            0x02, 0x08, 0xAB, 0xCD, // 0x20C: the words
+        ]
+    );
+}
+
+#[test]
+fn assemble_errors() {
+    let source = "
+        LD V0, 0x100
+        JP nowhere
+        FOO V1
+    a:  LD V1, DT
+    a:  SE V1, V2, V3
+        LD I, 1 + 
+X EQU X
+        LD V0, X
+    ";
+    assert_eq!(
+        errors(source),
+        [(2, 16), (3, 12), (4, 9), (6, 5), (6, 9), (7, 18), (8, 7)]
+    );
+    let error = &assemble("  JP 0x1000").unwrap_err()[0];
+    assert_eq!(error.to_string(), "1:6: 4096 doesn't fit in 0 - 0xFFF");
+}
+
+#[test]
+fn disassembled_roms_assemble_to_the_same_bytes() {
+    let roms = concat!(env!("CARGO_MANIFEST_DIR"), "/Roms");
+    for entry in fs::read_dir(roms).unwrap() {
+        let path = entry.unwrap().path();
+        let rom = fs::read(&path).unwrap();
+        for &mode in [Mode::Chip8, Mode::SuperChip, Mode::XoChip].iter() {
+            let source = disassemble_rom(&rom, mode, Syntax::Cowgod);
+            assert_eq!(assemble(&source), Ok(rom.clone()), "{:?}", path);
+        }
+    }
+}
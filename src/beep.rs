@@ -1,7 +1,11 @@
+use chip8_emulator::chip8::AUDIO_PATTERN_SIZE;
 use sdl2::audio::{AudioCallback, AudioSpecDesired};
 
+// the number of 1 bit samples in an audio pattern
+const PATTERN_BITS: f32 = (AUDIO_PATTERN_SIZE * 8) as f32;
+
 pub struct AudioDevice {
-    device: sdl2::audio::AudioDevice<SquareWave>,
+    device: sdl2::audio::AudioDevice<PatternWave>,
 }
 
 impl AudioDevice {
@@ -20,10 +24,12 @@ impl AudioDevice {
                 println!("{:?}", spec);
 
                 // initialize the audio callback
-                SquareWave {
-                    phase_inc: 440.0 / spec.freq as f32,
+                PatternWave {
+                    pattern: [0; AUDIO_PATTERN_SIZE],
+                    phase_inc: 0.0,
                     phase: 0.0,
                     volume: 0.25,
+                    freq: spec.freq as f32,
                 }
             })
             .unwrap();
@@ -34,8 +40,16 @@ impl AudioDevice {
             device
         }
     }
-    
-    pub fn beep(&self) {
+
+    // play the audio pattern at the given pitch (XO-CHIP's fx3a)
+    pub fn beep(&mut self, pattern: &[u8; AUDIO_PATTERN_SIZE], pitch: u8) {
+        {
+            let mut wave = self.device.lock();
+            wave.pattern = *pattern;
+            // samples per second: 4000 * 2 ^ ((pitch - 64) / 48)
+            let rate = 4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0);
+            wave.phase_inc = rate / wave.freq;
+        }
         self.device.resume();
     }
 
@@ -45,24 +59,27 @@ impl AudioDevice {
 
 }
 
-struct SquareWave {
+// Loops over the 128 1 bit samples of the pattern buffer
+struct PatternWave {
+    pattern: [u8; AUDIO_PATTERN_SIZE],
+    // pattern samples to advance per output sample
     phase_inc: f32,
+    // position in the pattern, in samples
     phase: f32,
     volume: f32,
+    // output sample rate
+    freq: f32,
 }
 
-impl AudioCallback for SquareWave {
+impl AudioCallback for PatternWave {
     type Channel = f32;
 
     fn callback(&mut self, out: &mut [f32]) {
-        // Generate a square wave
         for x in out.iter_mut() {
-            *x = if self.phase <= 0.5 {
-                self.volume
-            } else {
-                -self.volume
-            };
-            self.phase = (self.phase + self.phase_inc) % 1.0;
+            let bit = self.phase as usize;
+            let on = self.pattern[bit / 8] & (0x80 >> (bit % 8)) != 0;
+            *x = if on { self.volume } else { -self.volume };
+            self.phase = (self.phase + self.phase_inc) % PATTERN_BITS;
         }
     }
 }
@@ -0,0 +1,55 @@
+// chip8-asm: assemble a ROM from Cowgod's mnemonics
+use chip8_emulator::asm;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "Usage: chip8-asm <source> [options]
+Options:
+    -o <rom>            where to write the ROM (the source with a .ch8 extension by default)";
+
+fn main() {
+    let mut source = None;
+    let mut output = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => match args.next() {
+                Some(path) => output = Some(path),
+                None => usage("Expected a file after -o"),
+            },
+            _ if arg.starts_with('-') => usage(&format!("Unknown option: {}", arg)),
+            _ if source.is_none() => source = Some(arg),
+            _ => usage(&format!("Unexpected argument: {}", arg)),
+        }
+    }
+
+    let path = source.unwrap_or_else(|| usage("No source given"));
+    let output = output.unwrap_or_else(|| {
+        Path::new(&path)
+            .with_extension("ch8")
+            .to_string_lossy()
+            .into_owned()
+    });
+    let text = fs::read_to_string(&path).unwrap_or_else(|e| {
+        eprintln!("Couldn't read {}: {}", path, e);
+        process::exit(1);
+    });
+    let rom = asm::assemble(&text).unwrap_or_else(|errors| {
+        for error in errors.iter() {
+            eprintln!("{}:{}", path, error);
+        }
+        process::exit(1);
+    });
+    if let Err(e) = fs::write(&output, &rom) {
+        eprintln!("Couldn't write {}: {}", output, e);
+        process::exit(1);
+    }
+}
+
+fn usage(error: &str) -> ! {
+    eprintln!("{}\n{}", error, USAGE);
+    process::exit(1);
+}
@@ -0,0 +1,49 @@
+// chip8-disasm: print the assembly of a ROM
+use chip8_emulator::disasm::{self, Syntax};
+use chip8_emulator::Mode;
+use std::env;
+use std::fs;
+use std::process;
+
+const USAGE: &str = "Usage: chip8-disasm <rom> [options]
+Options:
+    --mode <name>       the instruction set: chip8, schip or xochip (chip8 by default)
+    --octo              print Octo code instead of Cowgod's mnemonics";
+
+fn main() {
+    let mut rom = None;
+    let mut mode = Mode::Chip8;
+    let mut syntax = Syntax::Cowgod;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--mode" => {
+                mode = match args.next().as_deref() {
+                    Some("chip8") => Mode::Chip8,
+                    Some("schip") => Mode::SuperChip,
+                    Some("xochip") => Mode::XoChip,
+                    _ => usage("Expected chip8, schip or xochip after --mode"),
+                }
+            }
+            "--octo" => syntax = Syntax::Octo,
+            _ if arg.starts_with("--") => usage(&format!("Unknown option: {}", arg)),
+            _ if rom.is_none() => rom = Some(arg),
+            _ => usage(&format!("Unexpected argument: {}", arg)),
+        }
+    }
+
+    let path = rom.unwrap_or_else(|| usage("No ROM given"));
+    match fs::read(&path) {
+        Ok(rom) => print!("{}", disasm::disassemble_rom(&rom, mode, syntax)),
+        Err(e) => {
+            eprintln!("Couldn't read {}: {}", path, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn usage(error: &str) -> ! {
+    eprintln!("{}\n{}", error, USAGE);
+    process::exit(1);
+}
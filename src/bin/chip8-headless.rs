@@ -0,0 +1,155 @@
+// chip8-headless: run a ROM without a window, then dump the screen and the registers
+use chip8_emulator::debugger::parse_number;
+use chip8_emulator::headless::{self, Stop, Until};
+use chip8_emulator::{quirks, Chip8, Mode, Palette, Quirks};
+use std::convert::TryFrom;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+
+const USAGE: &str = "Usage: chip8-headless <rom> [options]
+Options:
+    --profile <name>    the interpreter to emulate: default, vip, chip48, schip or xochip
+    --ipf <n>           instructions executed per frame
+    --seed <n>          seed of the random number generator (0 by default)
+    --frames <n>        the frames to run, 600 (10 seconds) by default
+    --until-pc <addr>   stop when pc reaches an address
+    --until-opcode <op> stop when the next instruction is an opcode
+    --keys <script>     key events: \"30 down 5; 90 up 5\" presses key 5 from frame 30 to 89
+    --keys-file <file>  key events read from a file, one per line
+    --ascii             print the screen as text (the default when nothing else is dumped)
+    --png <file>        write the screen as a PNG image
+    --scale <n>         the size of the pixels in the PNG image, 4 by default
+    --palette <p>       the colors of the PNG image: mono, green, amber, lcd or hex RGB colors
+    --json <file>       write the registers as JSON, - for the standard output
+Exits with 2 when --until-pc or --until-opcode wasn't reached.";
+
+// the frames run when --frames isn't given
+const DEFAULT_FRAMES: usize = 600;
+const DEFAULT_SCALE: usize = 4;
+
+fn main() {
+    let mut rom = None;
+    let mut mode = Mode::Chip8;
+    let mut quirks = Quirks::default();
+    let mut ipf = None;
+    let mut seed = 0;
+    let mut frames = DEFAULT_FRAMES;
+    let mut until = None;
+    let mut keys = String::new();
+    let mut ascii = false;
+    let mut png = None;
+    let mut scale = DEFAULT_SCALE;
+    let mut palette = Palette::default();
+    let mut json = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| usage(&format!("Missing value for {}", arg)))
+        };
+        match arg.as_str() {
+            "--profile" => {
+                let name = value();
+                let profile = quirks::profile(&name)
+                    .unwrap_or_else(|| usage(&format!("Unknown interpreter profile: {}", name)));
+                mode = profile.0;
+                quirks = profile.1;
+            }
+            "--ipf" => ipf = Some(number(&arg, &value()).max(1) as usize),
+            "--seed" => seed = number(&arg, &value()),
+            "--frames" => frames = number(&arg, &value()) as usize,
+            "--until-pc" => until = Some(Until::Pc(word(&arg, &value()))),
+            "--until-opcode" => until = Some(Until::Opcode(word(&arg, &value()))),
+            "--keys" => {
+                keys.push_str(&value());
+                keys.push('\n');
+            }
+            "--keys-file" => {
+                let path = value();
+                match fs::read_to_string(&path) {
+                    Ok(script) => keys.push_str(&script),
+                    Err(e) => fail(&format!("Couldn't read {}: {}", path, e)),
+                }
+                keys.push('\n');
+            }
+            "--ascii" => ascii = true,
+            "--png" => png = Some(value()),
+            "--scale" => scale = number(&arg, &value()).max(1) as usize,
+            "--palette" => {
+                palette = Palette::parse(&value()).unwrap_or_else(|e| usage(&e));
+            }
+            "--json" => json = Some(value()),
+            _ if arg.starts_with("--") => usage(&format!("Unknown option: {}", arg)),
+            _ if rom.is_none() => rom = Some(arg),
+            _ => usage(&format!("Unexpected argument: {}", arg)),
+        }
+    }
+
+    let path = rom.unwrap_or_else(|| usage("No ROM given"));
+    let keys = headless::parse_keys(&keys).unwrap_or_else(|e| usage(&format!("Keys: {}", e)));
+    let mut chip8 = Chip8::new_with_seed(quirks, seed);
+    chip8.set_mode(mode);
+    if let Err(e) = chip8.load_rom(&path) {
+        fail(&format!("Couldn't load ROM: {}", e));
+    }
+
+    let ipf = ipf.unwrap_or_else(|| mode.default_ipf());
+    // the state is dumped even after an error, it shows where the program went wrong
+    let outcome = headless::run(&mut chip8, ipf, frames, until, &keys);
+
+    if ascii || (png.is_none() && json.is_none()) {
+        print!("{}", headless::ascii(chip8.framebuffer()));
+    }
+    if let Some(path) = png {
+        let image = headless::png(chip8.framebuffer(), scale, &palette);
+        if let Err(e) = fs::write(&path, image) {
+            fail(&format!("Couldn't write {}: {}", path, e));
+        }
+    }
+    if let Some(path) = json {
+        let registers = headless::registers_json(&chip8, &outcome);
+        let written = if path == "-" {
+            io::stdout().write_all(registers.as_bytes())
+        } else {
+            fs::write(&path, registers)
+        };
+        if let Err(e) = written {
+            fail(&format!("Couldn't write {}: {}", path, e));
+        }
+    }
+
+    if let Stop::Error(e) = &outcome.stop {
+        fail(&format!("Execution error: {}", e));
+    }
+    if until.is_some() && outcome.stop == Stop::Frames {
+        eprintln!(
+            "Stopped after {} frames without reaching the condition",
+            frames
+        );
+        process::exit(2);
+    }
+}
+
+fn number(option: &str, value: &str) -> u64 {
+    parse_number(value)
+        .unwrap_or_else(|| usage(&format!("Invalid number for {}: {}", option, value)))
+}
+
+// an address or an opcode, up to 0xFFFF
+fn word(option: &str, value: &str) -> u16 {
+    u16::try_from(number(option, value))
+        .unwrap_or_else(|_| usage(&format!("{} takes up to 0xFFFF: {}", option, value)))
+}
+
+fn fail(error: &str) -> ! {
+    eprintln!("{}", error);
+    process::exit(1);
+}
+
+fn usage(error: &str) -> ! {
+    eprintln!("{}\n{}", error, USAGE);
+    process::exit(1);
+}
@@ -0,0 +1,38 @@
+// chip8-trace: print a binary trace (--trace-format binary) as text, to read or diff it
+use chip8_emulator::trace;
+use std::env;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::process;
+
+const USAGE: &str = "Usage: chip8-trace <trace>";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let path = match args.as_slice() {
+        [path] => path,
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+    let records = fs::read(path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| trace::from_bytes(&data));
+    let records = match records {
+        Ok(records) => records,
+        Err(e) => {
+            eprintln!("Couldn't read trace {}: {}", path, e);
+            process::exit(1);
+        }
+    };
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    for record in records.iter() {
+        // stop quietly when the output is closed, by head for example
+        if writeln!(out, "{}", record).is_err() {
+            return;
+        }
+    }
+    let _ = out.flush();
+}
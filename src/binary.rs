@@ -0,0 +1,109 @@
+// The little endian binary formats: save states, movies and traces. Each one starts with its
+// 4 byte magic and a version (u8), bumped whenever the layout of the format changes.
+pub struct Writer(Vec<u8>);
+
+impl Writer {
+    pub fn with_capacity(capacity: usize) -> Writer {
+        Writer(Vec::with_capacity(capacity))
+    }
+
+    pub fn header(&mut self, magic: &[u8; 4], version: u8) {
+        self.bytes(magic);
+        self.u8(version);
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.0.push(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+// The data ended in the middle of a value. Each format turns it into its own error
+#[derive(Debug, PartialEq)]
+pub struct Truncated;
+
+pub struct Reader<'a> {
+    // what's left to read
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data }
+    }
+
+    // The version of the format, None when the data doesn't start with magic
+    pub fn header(&mut self, magic: &[u8; 4]) -> Result<Option<u8>, Truncated> {
+        if self.bytes(magic.len())? != magic {
+            return Ok(None);
+        }
+        Ok(Some(self.u8()?))
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], Truncated> {
+        if self.data.len() < len {
+            return Err(Truncated);
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, Truncated> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, Truncated> {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(self.bytes(2)?);
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, Truncated> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, Truncated> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    // the bytes left to read
+    pub fn remaining(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+#[cfg(test)]
+#[path = "./binary_tests.rs"]
+mod binary_tests;
@@ -0,0 +1,39 @@
+use super::*;
+
+#[test]
+fn round_trip() {
+    let mut out = Writer::with_capacity(0);
+    out.header(b"TEST", 3);
+    out.u8(0xAB);
+    out.bool(true);
+    out.u16(0x1234);
+    out.u32(0x1234_5678);
+    out.u64(0x0123_4567_89AB_CDEF);
+    let data = out.into_bytes();
+    assert_eq!(data[..7], [b'T', b'E', b'S', b'T', 3, 0xAB, 1]);
+    assert_eq!(data[7..9], [0x34, 0x12]);
+
+    let mut input = Reader::new(&data);
+    assert_eq!(input.header(b"TEST"), Ok(Some(3)));
+    assert_eq!(input.u8(), Ok(0xAB));
+    assert_eq!(input.u8(), Ok(1));
+    assert_eq!(input.u16(), Ok(0x1234));
+    assert_eq!(input.u32(), Ok(0x1234_5678));
+    assert_eq!(input.remaining(), 8);
+    assert_eq!(input.u64(), Ok(0x0123_4567_89AB_CDEF));
+    assert!(input.is_empty());
+}
+
+#[test]
+fn wrong_magic() {
+    assert_eq!(Reader::new(b"C8MV\x01").header(b"C8ST"), Ok(None));
+}
+
+#[test]
+fn truncated() {
+    let mut input = Reader::new(&[1, 2, 3]);
+    assert_eq!(input.u32(), Err(Truncated));
+    // nothing is consumed
+    assert_eq!(input.u16(), Ok(0x0201));
+    assert_eq!(Reader::new(b"C8").header(b"C8ST"), Err(Truncated));
+}
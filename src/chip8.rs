@@ -1,14 +1,33 @@
-use rand::Rng;
-use std::error::Error;
+use crate::error::Chip8Error;
+use crate::framebuffer::Framebuffer;
+use crate::instruction::{decode, Instruction};
+use crate::quirks::Quirks;
+use crate::rng::Rng;
 use std::fmt;
 use std::fs;
-use std::{thread, time};
 
 const RAM_SIZE: usize = 4096;
-const NUM_REGISTERS: usize = 16;
-const STACK_SIZE: usize = 16;
+// XO-CHIP has a 64kb address space
+const XO_RAM_SIZE: usize = 65536;
+pub const NUM_REGISTERS: usize = 16;
+pub const STACK_SIZE: usize = 16;
+pub const NUM_KEYS: usize = 16;
 pub const WIDTH: usize = 64;
 pub const HEIGHT: usize = 32;
+// SUPER-CHIP high resolution mode
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+// SUPER-CHIP user flags (the HP-48 RPL registers)
+const NUM_RPL_FLAGS: usize = 8;
+// the big font is stored right after the small one
+const BIG_FONT_ADDRESS: usize = 0x50;
+// XO-CHIP audio: 16 bytes (128 samples) of 1 bit audio
+pub const AUDIO_PATTERN_SIZE: usize = 16;
+// the default pitch, the pattern is played at 4000 samples per second
+const DEFAULT_PITCH: u8 = 64;
+// fx0a with the beep quirk: the sound timer while the key is held, the VIP's tone lasts a few
+// frames after the release
+const KEY_WAIT_BEEP: u8 = 4;
 
 const CHIP8_FONTS: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
@@ -29,23 +48,104 @@ const CHIP8_FONTS: [u8; 80] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
+// SUPER-CHIP 8x10 font
+const SCHIP_BIG_FONTS: [u8; 160] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x18, 0x3C, 0x66, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFE, 0xC3, 0xC3, 0xFE, 0xFE, 0xC3, 0xC3, 0xFE, 0xFC, // B
+    0x3C, 0x7E, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0x7E, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+// The instruction set the machine understands
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    // the original 35 opcodes
+    Chip8,
+    // SUPER-CHIP 1.1: hires, scrolling, big sprites and fonts, RPL flags
+    SuperChip,
+    // XO-CHIP: SUPER-CHIP + 64kb memory, 2 drawing planes and audio patterns
+    XoChip,
+}
+
+impl Mode {
+    // the number identifying the mode in save states and movies
+    pub fn id(self) -> u8 {
+        match self {
+            Mode::Chip8 => 0,
+            Mode::SuperChip => 1,
+            Mode::XoChip => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Mode> {
+        match id {
+            0 => Some(Mode::Chip8),
+            1 => Some(Mode::SuperChip),
+            2 => Some(Mode::XoChip),
+            _ => None,
+        }
+    }
+
+    // the usual speed of the programs written for each interpreter, in instructions per frame
+    pub fn default_ipf(self) -> usize {
+        match self {
+            Mode::Chip8 => 10,
+            Mode::SuperChip => 30,
+            Mode::XoChip => 1000,
+        }
+    }
+}
+
+// What the machine accessed memory for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+    // reading the instruction at pc (and the operand of f000)
+    Fetch,
+    // an instruction reading data: sprites, fx65, 5xy3, f002
+    Read,
+    // an instruction writing data: fx33, fx55, 5xy2
+    Write,
+}
+
+// Told about every memory access the machine makes, used by the debugger watchpoints
+pub trait MemoryObserver {
+    // value is the byte read, or the byte written
+    fn on_access(&mut self, addr: usize, access: Access, value: u8);
+}
+
 pub struct Chip8 {
-    // Memory: 4kb of 8 bits(byte)
-    memory: [u8; RAM_SIZE],
+    // Memory: 4kb of 8 bits(byte), 64kb in XO-CHIP mode
+    memory: Vec<u8>,
     // Pc: the program counter, 16 bits but uses only 12 (max address is 0xfff)
     pc: u16,
     // General purpose registers
     v: [u8; NUM_REGISTERS],
     // I register: 16 bit memory holder
     i: u16,
-    //Two timers, they are not implemented yet.
-    pub sound_timer: u8,
+    //Two timers, they count down at 60hz
+    sound_timer: u8,
 
     delay_timer: u8,
     // Display
-    pub display: [[u8; WIDTH]; HEIGHT],
+    display: Framebuffer,
 
-    pub keyboard: [bool; 16],
+    // the keys held, kept until they're released
+    keyboard: [bool; NUM_KEYS],
+    // the keys pressed and released since the last frame (bitmasks, bit n is key n)
+    pressed_keys: u16,
+    released_keys: u16,
 
     // STACK
     stack: [u16; STACK_SIZE],
@@ -54,8 +154,30 @@ pub struct Chip8 {
     sp: u8,
 
     // random number handler
-    rng: rand::rngs::ThreadRng,
-    pub draw: bool,
+    rng: Rng,
+    // set when the display changed since the frontend last drew it
+    draw: bool,
+
+    // the instruction set
+    mode: Mode,
+    // the interpretation of the ambiguous opcodes
+    quirks: Quirks,
+    // SUPER-CHIP RPL user flags, saved/restored by fx75/fx85
+    rpl: [u8; NUM_RPL_FLAGS],
+    // set when the program executed 00fd (exit)
+    exited: bool,
+    // XO-CHIP planes selected for drawing, scrolling and clearing (bitmask)
+    planes: u8,
+    // XO-CHIP 1 bit audio samples, played while the sound timer is active
+    audio_pattern: [u8; AUDIO_PATTERN_SIZE],
+    // XO-CHIP playback rate of the audio pattern
+    pitch: u8,
+    // set on every timer tick, consumed by dxyn when the display wait quirk is on
+    vblank: bool,
+    // fx0a: the key pressed, waiting to be released
+    key_wait: Option<u8>,
+    // reported every memory access when set
+    observer: Option<Box<dyn MemoryObserver>>,
 }
 
 impl fmt::Display for Chip8 {
@@ -70,11 +192,16 @@ impl fmt::Display for Chip8 {
 
 impl Chip8 {
     // This function creates a new instance of the chip struct
-    pub fn new() -> Chip8 {
-        let mut ram = [0u8; RAM_SIZE];
-        for i in 0..CHIP8_FONTS.len() {
-            ram[i] = CHIP8_FONTS[i];
-        }
+    pub fn new(quirks: Quirks) -> Chip8 {
+        Chip8::new_with_seed(quirks, rand::random())
+    }
+
+    // A new instance whose random numbers (cxkk) are the same on every run with the same seed
+    pub fn new_with_seed(quirks: Quirks, seed: u64) -> Chip8 {
+        let mut ram = vec![0u8; RAM_SIZE];
+        ram[..CHIP8_FONTS.len()].copy_from_slice(&CHIP8_FONTS);
+        ram[BIG_FONT_ADDRESS..BIG_FONT_ADDRESS + SCHIP_BIG_FONTS.len()]
+            .copy_from_slice(&SCHIP_BIG_FONTS);
 
         Chip8 {
             // FIll memory with zeros
@@ -84,162 +211,444 @@ impl Chip8 {
             // FILL registers with 0
             v: [0; NUM_REGISTERS],
             i: 0x0,
-            display: [[0; WIDTH]; HEIGHT],
-            keyboard: [false; 16],
+            display: Framebuffer::new(WIDTH, HEIGHT),
+            keyboard: [false; NUM_KEYS],
+            pressed_keys: 0,
+            released_keys: 0,
             sound_timer: 0,
             delay_timer: 0,
             stack: [0; STACK_SIZE],
             sp: 0,
-            rng: rand::thread_rng(),
+            rng: Rng::new(seed),
             draw: false,
+            mode: Mode::Chip8,
+            quirks,
+            rpl: [0; NUM_RPL_FLAGS],
+            exited: false,
+            planes: 0x1,
+            audio_pattern: [0xF0; AUDIO_PATTERN_SIZE],
+            pitch: DEFAULT_PITCH,
+            vblank: false,
+            key_wait: None,
+            observer: None,
         }
     }
+
+    // Select the instruction set, must be done before the ROM starts running
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        let size = if mode == Mode::XoChip {
+            XO_RAM_SIZE
+        } else {
+            RAM_SIZE
+        };
+        self.memory.resize(size, 0);
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    // Read-only access to the machine state
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn v(&self) -> &[u8; NUM_REGISTERS] {
+        &self.v
+    }
+
+    pub fn i(&self) -> u16 {
+        self.i
+    }
+
+    pub fn sp(&self) -> u8 {
+        self.sp
+    }
+
+    // the whole stack, only the first sp entries are in use
+    pub fn stack(&self) -> &[u16; STACK_SIZE] {
+        &self.stack
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.display
+    }
+
+    pub fn keys(&self) -> &[bool; NUM_KEYS] {
+        &self.keyboard
+    }
+
+    // The keys pressed and released since the last frame ended, bit n is key n. A key tapped
+    // between two frames is in both
+    pub fn key_edges(&self) -> (u16, u16) {
+        (self.pressed_keys, self.released_keys)
+    }
+
+    // press one of the 16 keys (0x0 - 0xF), it stays held until key_up
+    pub fn key_down(&mut self, key: usize) {
+        if let Some(state) = self.keyboard.get_mut(key) {
+            if !*state {
+                self.pressed_keys |= 1 << key;
+            }
+            *state = true;
+        }
+    }
+
+    pub fn key_up(&mut self, key: usize) {
+        if let Some(state) = self.keyboard.get_mut(key) {
+            if *state {
+                self.released_keys |= 1 << key;
+            }
+            *state = false;
+        }
+    }
+
+    pub fn set_key(&mut self, key: usize, pressed: bool) {
+        if pressed {
+            self.key_down(key);
+        } else {
+            self.key_up(key);
+        }
+    }
+
+    // held, or tapped since the last frame: a tap lasts until the end of the frame
+    fn key_active(&self, key: usize) -> bool {
+        self.keyboard[key] || self.pressed_keys & (1 << key) != 0
+    }
+
+    // the debugger edits the registers directly
+    pub fn set_v(&mut self, x: usize, value: u8) {
+        if let Some(register) = self.v.get_mut(x) {
+            *register = value;
+        }
+    }
+
+    pub fn set_i(&mut self, value: u16) {
+        self.i = value;
+    }
+
+    pub fn set_pc(&mut self, value: u16) {
+        self.pc = value;
+    }
+
+    pub fn set_delay_timer(&mut self, value: u8) {
+        self.delay_timer = value;
+    }
+
+    pub fn set_sound_timer(&mut self, value: u8) {
+        self.sound_timer = value;
+    }
+
+    // the debugger steps a paused machine, with no frames: a draw doesn't wait for a vertical
+    // blank that would never come
+    pub fn set_vblank(&mut self) {
+        self.vblank = true;
+    }
+
+    // Report the memory accesses to observer from now on, or stop reporting them
+    pub fn set_observer(&mut self, observer: Option<Box<dyn MemoryObserver>>) {
+        self.observer = observer;
+    }
+
+    // true when the display changed since the last call, the frontend should redraw it
+    pub fn take_draw(&mut self) -> bool {
+        std::mem::replace(&mut self.draw, false)
+    }
+
+    // true once the program executed 00fd (SUPER-CHIP exit)
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    // The XO-CHIP audio pattern to play while the sound timer is active
+    pub fn audio_pattern(&self) -> &[u8; AUDIO_PATTERN_SIZE] {
+        &self.audio_pattern
+    }
+
+    // The rate at which the audio pattern is played: 4000 * 2 ^ ((pitch - 64) / 48) Hz
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
     // This function loads a rom to memory
-    pub fn load_rom(&mut self, rom_path: &str) -> Result<(), Box<dyn Error>> {
+    pub fn load_rom(&mut self, rom_path: &str) -> Result<(), Chip8Error> {
         // Read ROM
         let rom_data = fs::read(rom_path)?;
-        let mut memory_pointer = 0x200;
-        for &byte in rom_data.iter() {
-            self.memory[memory_pointer] = byte;
-            memory_pointer += 1;
-        }
-        Ok(())
+        self.load_rom_bytes(&rom_data)
     }
 
-    //For debuging, this function will print memory from the requested location
-    #[allow(dead_code)]
-    pub fn print_memory(&mut self, start_index: u16) {
-        for pointer in start_index..RAM_SIZE as u16 {
-            println!("{:#X}: {:#04X}", pointer, self.memory[pointer as usize]);
-            thread::sleep(time::Duration::from_millis(10));
+    // Load a rom already in memory at 0x200
+    pub fn load_rom_bytes(&mut self, rom_data: &[u8]) -> Result<(), Chip8Error> {
+        if rom_data.len() > self.memory.len() - 0x200 {
+            return Err(Chip8Error::RomTooLarge {
+                size: rom_data.len(),
+            });
         }
+        self.memory[0x200..0x200 + rom_data.len()].copy_from_slice(rom_data);
+        Ok(())
     }
 
-    pub fn cycle(&mut self) -> Result<(), String> {
+    // execute a single instruction, the timers are not affected (see run_frame)
+    pub fn step(&mut self) -> Result<(), Chip8Error> {
+        if self.exited {
+            return Ok(());
+        }
         // Decode the opcode:
-        let hi = self.memory[self.pc as usize] as u16;
-        let lo = self.memory[(self.pc + 1) as usize] as u16;
-        let opcode = (hi << 8) | lo;
-        let nibbles = (
-            (opcode & 0xF000) >> 12 as u8,
-            (opcode & 0x0F00) >> 8 as u8,
-            (opcode & 0x00F0) >> 4 as u8,
-            (opcode & 0x000F) as u8,
-        );
-
-        // extract data from the opcode: kk, nnn, n, nn x, y
-        let nnn = opcode & 0x0FFF;
-        let kk: u8 = (opcode & 0x00FF) as u8; // or nn
-        let n: u8 = (opcode & 0x000F) as u8;
-        let x: u8 = ((opcode >> 8) & 0x000F) as u8;
-        let y: u8 = ((opcode >> 4) & 0x000F) as u8;
-        // println!("\nOpcode: {:#06X}", opcode);
-        // println!(
-        //     "nnn: {:#05X}; kk: {:#04X}; n: {:#03X}; x: {:#03X}; y: {:#03X}",
-        //     nnn, kk, n, x, y
-        // );
-        // println!("State: {}", self);
-        //Wait for input to proceed
-        //let mut input = String::from("");
-        // io::stdin()
-        //     .read_line(&mut input)
-        //     .ok()
-        //     .expect("Couldn't read line");
-
-        match nibbles {
+        let opcode = self.read_word(self.pc as usize, Access::Fetch)?;
+        let instruction = decode(opcode);
+        // SUPER-CHIP opcodes are unknown on a plain CHIP-8, XO-CHIP extends SUPER-CHIP
+        if !instruction.supported_by(self.mode) {
+            return Err(Chip8Error::UnknownOpcode {
+                pc: self.pc,
+                opcode,
+            });
+        }
+
+        match instruction {
             // Clear dispaly
-            (0x0, 0x0, 0xE, 0x0) => self.op_00e0(),
+            Instruction::Cls => self.op_00e0(),
             // RET - return from subroutine
-            (0x0, 0x0, 0xE, 0xE) => self.op_00ee()?,
+            Instruction::Ret => self.op_00ee()?,
+            // scroll down n pixels
+            Instruction::ScrollDown(n) => self.op_00cn(n),
+            // scroll up n pixels
+            Instruction::ScrollUp(n) => self.op_00dn(n),
+            // scroll right 4 pixels
+            Instruction::ScrollRight => self.op_00fb(),
+            // scroll left 4 pixels
+            Instruction::ScrollLeft => self.op_00fc(),
+            // exit the interpreter
+            Instruction::Exit => self.op_00fd(),
+            // lores mode (64x32)
+            Instruction::Lores => self.op_00fe(),
+            // hires mode (128x64)
+            Instruction::Hires => self.op_00ff(),
             // 1nnn: sets pc to nnn
-            (0x1, _, _, _) => self.op_1nnn(nnn),
+            Instruction::Jump(nnn) => self.op_1nnn(nnn),
             //call - push pc to stack and jump to nnn
-            (0x2, _, _, _) => self.op_2nnn(nnn)?,
+            Instruction::Call(nnn) => self.op_2nnn(nnn)?,
             // skip next intruction if vx == kk
-            (0x3, _, _, _) => self.op_3xkk(x, kk),
+            Instruction::SkipEqImm(x, kk) => self.op_3xkk(x, kk),
             // skip next intruction if vx != kk
-            (0x4, _, _, _) => self.op_4xkk(x, kk),
+            Instruction::SkipNeImm(x, kk) => self.op_4xkk(x, kk),
             // skip next intruction if vx == vy
-            (0x5, _, _, 0x0) => self.op_5xy0(x, y),
+            Instruction::SkipEq(x, y) => self.op_5xy0(x, y),
+            // save vx through vy in memory starting at I
+            Instruction::StoreRange(x, y) => self.op_5xy2(x, y)?,
+            // load vx through vy from memory starting at I
+            Instruction::LoadRange(x, y) => self.op_5xy3(x, y)?,
             // sets vx to kk
-            (0x6, _, _, _) => self.op_6xkk(x, kk),
+            Instruction::LoadImm(x, kk) => self.op_6xkk(x, kk),
             // adds kk v[x], store in v[x]
-            (0x7, _, _, _) => self.op_7xkk(x, kk),
+            Instruction::AddImm(x, kk) => self.op_7xkk(x, kk),
             // sets v[x] = v[y]
-            (0x8, _, _, 0x0) => self.op_8xy0(x, y),
+            Instruction::Move(x, y) => self.op_8xy0(x, y),
             // sets v[x] = v[x] | v[y]
-            (0x8, _, _, 0x1) => self.op_8xy1(x, y),
+            Instruction::Or(x, y) => self.op_8xy1(x, y),
             // sets v[x] = v[x] & v[y]
-            (0x8, _, _, 0x2) => self.op_8xy2(x, y),
+            Instruction::And(x, y) => self.op_8xy2(x, y),
             // sets v[x] = v[x] ^ v[y]
-            (0x8, _, _, 0x3) => self.op_8xy3(x, y),
+            Instruction::Xor(x, y) => self.op_8xy3(x, y),
             // adds vx and vy
-            (0x8, _, _, 0x4) => self.op_8xy4(x, y),
+            Instruction::Add(x, y) => self.op_8xy4(x, y),
             // subbstructs vy from vx
-            (0x8, _, _, 0x5) => self.op_8xy5(x, y),
+            Instruction::Sub(x, y) => self.op_8xy5(x, y),
             // shift right
-            (0x8, _, _, 0x6) => self.op_8xy6(x, y),
+            Instruction::ShiftRight(x, y) => self.op_8xy6(x, y),
             // substrcut vy from vx store in vx
-            (0x8, _, _, 0x7) => self.op_8xy7(x, y),
+            Instruction::SubN(x, y) => self.op_8xy7(x, y),
             // left shift (multiply by two)
-            (0x8, _, _, 0xE) => self.op_8xye(x, y),
+            Instruction::ShiftLeft(x, y) => self.op_8xye(x, y),
             // skip next instruction if vx!=vy
-            (0x9, _, _, 0x0) => self.op_9xy0(x, y),
+            Instruction::SkipNe(x, y) => self.op_9xy0(x, y),
             // set I to nnn
-            (0xA, _, _, _) => self.op_annn(nnn),
+            Instruction::LoadI(nnn) => self.op_annn(nnn),
             // set B to nnn + v0
-            (0xB, _, _, _) => self.op_bnnn(nnn),
+            Instruction::JumpOffset(x, nnn) => self.op_bnnn(x, nnn),
             // generate random number
-            (0xC, _, _, _) => self.op_cxkk(x, kk),
+            Instruction::Random(x, kk) => self.op_cxkk(x, kk),
+            // draw a 16x16 sprite
+            Instruction::Draw(x, y, 0) if self.mode != Mode::Chip8 => self.op_dxy0(x, y)?,
             // draw to screen
-            (0xD, _, _, _) => self.op_dxyn(x, y, n),
-            // skip next instruction if keyboard[x] is pressed
-            (0xE, _, 0x9, 0xE) => self.op_ex9e(x),
-            // skip next instruction if keyboard[x] is not pressed
-            (0xE, _, 0xA, 0x1) => self.op_exa1(x),
+            Instruction::Draw(x, y, n) => self.op_dxyn(x, y, n)?,
+            // skip next instruction if the key in vx is pressed
+            Instruction::SkipKey(x) => self.op_ex9e(x),
+            // skip next instruction if the key in vx is not pressed
+            Instruction::SkipNotKey(x) => self.op_exa1(x),
+            // I = the 16 bit address in the next 2 bytes
+            Instruction::LoadILong => self.op_f000()?,
+            // select the drawing planes
+            Instruction::Plane(n) => self.op_fn01(n),
+            // load the audio pattern from memory at I
+            Instruction::Audio => self.op_f002()?,
             // vx = delay timer
-            (0xF, _, 0x0, 0x7) => self.op_fx07(x),
-            // wait for keypress, store result in vx
-            (0xF, _, 0x0, 0xA) => self.op_fx0a(x),
+            Instruction::GetDelay(x) => self.op_fx07(x),
+            // wait for a key, store it in vx
+            Instruction::WaitKey(x) => self.op_fx0a(x),
             // set dt=vx
-            (0xF, _, 0x1, 0x5) => self.op_fx15(x),
+            Instruction::SetDelay(x) => self.op_fx15(x),
             // set dt = vx
-            (0xF, _, 0x1, 0x8) => self.op_fx18(x),
+            Instruction::SetSound(x) => self.op_fx18(x),
             // i = i + vx
-            (0xF, _, 0x1, 0xE) => self.op_fx1e(x),
+            Instruction::AddI(x) => self.op_fx1e(x),
             // Set I = location of sprite for digit Vx.
-            (0xF, _, 0x2, 0x9) => self.op_fx29(x),
+            Instruction::Font(x) => self.op_fx29(x),
+            // Set I = location of the big sprite for digit Vx.
+            Instruction::BigFont(x) => self.op_fx30(x),
+            // set the audio pattern pitch to vx
+            Instruction::Pitch(x) => self.op_fx3a(x),
             // SStore BCD representation of Vx in memory locations I, I+1, and I+2.
-            (0xF, _, 0x3, 0x3) => self.op_fx33(x),
+            Instruction::Bcd(x) => self.op_fx33(x)?,
             // Store registers V0 through Vx in memory starting at location I.
-            (0xF, _, 0x5, 0x5) => self.op_fx55(x),
+            Instruction::StoreRegisters(x) => self.op_fx55(x)?,
             // Read registers V0 through Vx from memory starting at location I.
-            (0xF, _, 0x6, 0x5) => self.op_fx65(x),
-            _ => return Err(format!("Unknown intruction: {:#06X}", opcode)),
+            Instruction::LoadRegisters(x) => self.op_fx65(x)?,
+            // store V0 through Vx in the RPL flags
+            Instruction::StoreFlags(x) => self.op_fx75(x),
+            // read V0 through Vx from the RPL flags
+            Instruction::LoadFlags(x) => self.op_fx85(x),
+            // rejected above
+            Instruction::Unknown(_) => {}
+        }
+        Ok(())
+    }
+
+    // read a byte of memory, fails outside of the address space
+    fn read_byte(&mut self, addr: usize, access: Access) -> Result<u8, Chip8Error> {
+        let value = self
+            .memory
+            .get(addr)
+            .copied()
+            .ok_or(Chip8Error::MemoryOutOfBounds { addr })?;
+        if let Some(observer) = &mut self.observer {
+            observer.on_access(addr, access, value);
         }
+        Ok(value)
+    }
 
+    // read 2 bytes of memory (big endian)
+    fn read_word(&mut self, addr: usize, access: Access) -> Result<u16, Chip8Error> {
+        let hi = self.read_byte(addr, access)? as u16;
+        let lo = self.read_byte(addr + 1, access)? as u16;
+        Ok((hi << 8) | lo)
+    }
+
+    // write a byte of memory, fails outside of the address space
+    fn write_byte(&mut self, addr: usize, value: u8) -> Result<(), Chip8Error> {
+        match self.memory.get_mut(addr) {
+            Some(byte) => *byte = value,
+            None => return Err(Chip8Error::MemoryOutOfBounds { addr }),
+        }
+        if let Some(observer) = &mut self.observer {
+            observer.on_access(addr, Access::Write, value);
+        }
+        Ok(())
+    }
+
+    // Run one 60hz frame: execute ipf instructions, then tick the timers once
+    pub fn run_frame(&mut self, ipf: usize) -> Result<(), Chip8Error> {
+        for _ in 0..ipf {
+            if self.exited {
+                break;
+            }
+            self.step()?;
+        }
         self.handle_timers();
         Ok(())
     }
 
-    fn handle_timers(&mut self) {
+    // the timers count down at 60hz, this is called once per frame. It ends the frame: the key
+    // edges start over
+    pub fn handle_timers(&mut self) {
         if self.delay_timer > 0 {
             self.delay_timer -= 1;
         }
         if self.sound_timer > 0 {
             self.sound_timer -= 1;
         }
+        self.vblank = true;
+        self.pressed_keys = 0;
+        self.released_keys = 0;
     }
 
     // clear display
     fn op_00e0(&mut self) {
-        for y in 0..HEIGHT {
-            for x in 0..WIDTH {
-                self.display[y][x] = 0;
-            }
-        }
+        self.display.clear(self.planes);
         self.draw = true;
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // scroll the display n pixels down
+    fn op_00cn(&mut self, n: u8) {
+        self.display.scroll_down(n as usize, self.planes);
+        self.draw = true;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // scroll the display n pixels up
+    fn op_00dn(&mut self, n: u8) {
+        self.display.scroll_up(n as usize, self.planes);
+        self.draw = true;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // scroll the display 4 pixels right
+    fn op_00fb(&mut self) {
+        self.display.scroll_right(4, self.planes);
+        self.draw = true;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // scroll the display 4 pixels left
+    fn op_00fc(&mut self) {
+        self.display.scroll_left(4, self.planes);
+        self.draw = true;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // exit the interpreter, the machine stops executing
+    fn op_00fd(&mut self) {
+        self.exited = true;
+    }
+
+    // switch to lores (64x32), clears the display
+    fn op_00fe(&mut self) {
+        self.display.resize(WIDTH, HEIGHT);
+        self.draw = true;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // switch to hires (128x64), clears the display
+    fn op_00ff(&mut self) {
+        self.display.resize(HIRES_WIDTH, HIRES_HEIGHT);
+        self.draw = true;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // skip the next instruction. f000 nnnn is 4 bytes long, so skipping it takes 6 bytes
+    fn skip_next(&mut self) {
+        let next = self.pc.wrapping_add(2) as usize;
+        self.pc = self.pc.wrapping_add(4);
+        // a peek, not a fetch: the observer isn't told
+        if self.mode == Mode::XoChip && self.memory.get(next..next + 2) == Some(&[0xF0, 0x00][..]) {
+            self.pc = self.pc.wrapping_add(2);
+        }
     }
 
     // sets pc to whatever nnn is
@@ -248,22 +657,22 @@ impl Chip8 {
     }
 
     // pushes pc to the stack, and sets pc to nnn and increment sp
-    fn op_2nnn(&mut self, nnn: u16) -> Result<(), String> {
+    fn op_2nnn(&mut self, nnn: u16) -> Result<(), Chip8Error> {
         // check if we are not out of frames
         if self.sp as usize == STACK_SIZE {
-            return Err(String::from("Stack is full"));
+            return Err(Chip8Error::StackOverflow);
         }
-        self.stack[self.sp as usize] = self.pc + 2; // push the next instruction
+        self.stack[self.sp as usize] = self.pc.wrapping_add(2); // push the next instruction
         self.sp += 1; // increment
         self.pc = nnn; // jump
         Ok(())
     }
 
     // return from subroutine - pop address on stack to pc
-    fn op_00ee(&mut self) -> Result<(), String> {
+    fn op_00ee(&mut self) -> Result<(), Chip8Error> {
         if self.sp == 0 {
             // it shouldn't happened, just in case
-            return Err(String::from("Tried to return to none?"));
+            return Err(Chip8Error::StackUnderflow);
         }
         self.sp -= 1;
         self.pc = self.stack[self.sp as usize];
@@ -273,30 +682,60 @@ impl Chip8 {
     // compare vx to xx and increment pc by two if they are equal (+=2);
     fn op_3xkk(&mut self, x: u8, kk: u8) {
         if self.v[x as usize] == kk {
-            self.pc += 0x4; //skip additional 2bytes!
+            self.skip_next();
         } else {
             // increment the program counter
-            self.pc += 0x2;
+            self.pc = self.pc.wrapping_add(0x2);
         }
     }
 
     // compare vx to xx and increment pc by two if they are not equal (+=2);
     fn op_4xkk(&mut self, x: u8, kk: u8) {
         if self.v[x as usize] != kk {
-            self.pc += 4; //skip additional 2bytes!
+            self.skip_next();
         } else {
             // increment the program counter
-            self.pc += 0x2;
+            self.pc = self.pc.wrapping_add(0x2);
         }
     }
 
     // compare vx and vy and increment pc by two if they are equal (+=2);
     fn op_5xy0(&mut self, x: u8, y: u8) {
         if self.v[x as usize] == self.v[y as usize] {
-            self.pc += 4; //skip additional 2bytes!
+            self.skip_next();
         } else {
             // increment the program counter
-            self.pc += 0x2;
+            self.pc = self.pc.wrapping_add(0x2);
+        }
+    }
+
+    // save vx through vy (in either order) in memory starting at I, I is not changed
+    fn op_5xy2(&mut self, x: u8, y: u8) -> Result<(), Chip8Error> {
+        let pointer = self.i as usize;
+        for (offset, register) in Chip8::register_range(x, y).into_iter().enumerate() {
+            self.write_byte(pointer + offset, self.v[register])?;
+        }
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
+    }
+
+    // load vx through vy (in either order) from memory starting at I, I is not changed
+    fn op_5xy3(&mut self, x: u8, y: u8) -> Result<(), Chip8Error> {
+        let pointer = self.i as usize;
+        for (offset, register) in Chip8::register_range(x, y).into_iter().enumerate() {
+            self.v[register] = self.read_byte(pointer + offset, Access::Read)?;
+        }
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
+    }
+
+    // the registers from vx to vy, counting down if x > y
+    fn register_range(x: u8, y: u8) -> Vec<usize> {
+        let (x, y) = (x as usize, y as usize);
+        if x <= y {
+            (x..=y).collect()
+        } else {
+            (y..=x).rev().collect()
         }
     }
 
@@ -304,42 +743,51 @@ impl Chip8 {
     fn op_6xkk(&mut self, x: u8, kk: u8) {
         self.v[x as usize] = kk;
         // increment the program counter
-        self.pc += 0x2;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     // add kk to v[x]
     fn op_7xkk(&mut self, x: u8, kk: u8) {
         self.v[x as usize] = self.v[x as usize].wrapping_add(kk);
         // increment the program counter
-        self.pc += 0x2;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     // store vy in vx
     fn op_8xy0(&mut self, x: u8, y: u8) {
         self.v[x as usize] = self.v[y as usize];
         // increment the program counter
-        self.pc += 0x2;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     // set vx = vx | vy
     fn op_8xy1(&mut self, x: u8, y: u8) {
-        self.v[x as usize] = self.v[x as usize] | self.v[y as usize];
+        self.v[x as usize] |= self.v[y as usize];
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
         // increment the program counter
-        self.pc += 0x2;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     // set vx = vx & vy
     fn op_8xy2(&mut self, x: u8, y: u8) {
-        self.v[x as usize] = self.v[x as usize] & self.v[y as usize];
+        self.v[x as usize] &= self.v[y as usize];
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
         // increment the program counter
-        self.pc += 0x2;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     // set vx = vx & vy
     fn op_8xy3(&mut self, x: u8, y: u8) {
-        self.v[x as usize] = self.v[x as usize] ^ self.v[y as usize];
+        self.v[x as usize] ^= self.v[y as usize];
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
         // increment the program counter
-        self.pc += 0x2;
+        self.pc = self.pc.wrapping_add(0x2);
     }
     // adds vx and vy; turns on carry flag if necessery;
     fn op_8xy4(&mut self, x: u8, y: u8) {
@@ -353,7 +801,7 @@ impl Chip8 {
             self.v[0xF] = 0x0;
         }
         // increment the program counter
-        self.pc += 0x2;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     // substructs vy from vx. if vx > vy vf is set to one.
@@ -365,16 +813,21 @@ impl Chip8 {
         }
         self.v[x as usize] = self.v[x as usize].wrapping_sub(self.v[y as usize]);
         // increment the program counter
-        self.pc += 0x2;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     // shift right. if lsb of vx is 1, carry flag is turned on
-    fn op_8xy6(&mut self, x: u8, _y: u8) {
-        let lsb = self.v[x as usize] & 0x1; // extract the lsb
-        self.v[0xf] = lsb;
-        self.v[x as usize] >>= 1;
+    fn op_8xy6(&mut self, x: u8, y: u8) {
+        let value = if self.quirks.shift_uses_vy {
+            self.v[y as usize]
+        } else {
+            self.v[x as usize]
+        };
+        self.v[x as usize] = value >> 1;
+        // vf is the lsb
+        self.v[0xf] = value & 0x1;
         // increment the program counter
-        self.pc += 0x2;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     // substract. if vy > vx vf is set to one.
@@ -386,160 +839,313 @@ impl Chip8 {
         }
         self.v[x as usize] = self.v[y as usize].wrapping_sub(self.v[x as usize]);
         // increment the program counter
-        self.pc += 0x2;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     // shift left. if msb of vx is 1, carry flag is turned on
-    fn op_8xye(&mut self, x: u8, _y: u8) {
-        let msb = (self.v[x as usize] >> 7) & 0x1; // extract the msb
-        self.v[0xf] = msb;
-        self.v[x as usize] <<= 1;
+    fn op_8xye(&mut self, x: u8, y: u8) {
+        let value = if self.quirks.shift_uses_vy {
+            self.v[y as usize]
+        } else {
+            self.v[x as usize]
+        };
+        self.v[x as usize] = value << 1;
+        // vf is the msb
+        self.v[0xf] = (value >> 7) & 0x1;
         // increment the program counter
-        self.pc += 0x2;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     // if the two registers aren't equal, skip the next instruction
     fn op_9xy0(&mut self, x: u8, y: u8) {
         if self.v[x as usize] != self.v[y as usize] {
-            self.pc += 4; //skip additional 2bytes!
+            self.skip_next();
         } else {
             // increment the program counter
-            self.pc += 0x2;
+            self.pc = self.pc.wrapping_add(0x2);
         }
     }
 
     // set i = nnn
     fn op_annn(&mut self, nnn: u16) {
         self.i = nnn;
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
 
-    // set pc = nnn + v0
-    fn op_bnnn(&mut self, nnn: u16) {
-        self.pc = nnn + (self.v[0x0] as u16);
+    // set pc = nnn + v0 (or xnn + vx)
+    fn op_bnnn(&mut self, x: u8, nnn: u16) {
+        let register = if self.quirks.jump_uses_vx { x } else { 0x0 };
+        self.pc = nnn + (self.v[register as usize] as u16);
     }
 
     // set vx = random number & kk
     fn op_cxkk(&mut self, x: u8, kk: u8) {
-        let random_number: u16 = self.rng.gen_range(0, 256);
-        self.v[x as usize] = random_number as u8 & kk;
-        self.pc += 2;
-    }
-
-    fn op_dxyn(&mut self, x: u8, y: u8, n: u8) {
-        let mut pixel: u8;
-        self.v[0xF] = 0;
-        let x = self.v[x as usize];
-        let y = self.v[y as usize];
-        for yline in 0..n {
-            pixel = self.memory[self.i as usize + yline as usize];
-            for xline in 0..8 {
-                // sprites are 8 pixel tall
-                if (pixel & (0x80 >> xline)) != 0 {
-                    // determine for each byte if it is on
-                    // detect coliision
-                    let x_cord: usize = (x + xline) as usize;
-                    let y_cord: usize = (y + yline) as usize;
-                    if self.display[y_cord][x_cord] == 1 {
-                        self.v[0xF] = 1;
+        let random_number = self.rng.next_u8();
+        self.v[x as usize] = random_number & kk;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    fn op_dxyn(&mut self, x: u8, y: u8, n: u8) -> Result<(), Chip8Error> {
+        if self.quirks.display_wait {
+            // keep executing this instruction until the next vertical blank
+            if !self.vblank {
+                return Ok(());
+            }
+            self.vblank = false;
+        }
+        self.draw_sprite(x, y, 8, n as usize)?;
+        self.pc = self.pc.wrapping_add(2);
+        self.draw = true;
+        Ok(())
+    }
+
+    // draw a 16x16 sprite, 2 bytes per row
+    fn op_dxy0(&mut self, x: u8, y: u8) -> Result<(), Chip8Error> {
+        self.draw_sprite(x, y, 16, 16)?;
+        self.pc = self.pc.wrapping_add(2);
+        self.draw = true;
+        Ok(())
+    }
+
+    // xor a width x height sprite located at I to the display at (vx, vy), vf is set on collision.
+    // When 2 planes are selected, the sprite of the second plane follows the first one in memory
+    fn draw_sprite(&mut self, x: u8, y: u8, width: usize, height: usize) -> Result<(), Chip8Error> {
+        let (screen_width, screen_height) = (self.display.width(), self.display.height());
+        let bytes_per_row = width / 8;
+        // the starting position wraps around the screen
+        let x = self.v[x as usize] as usize % screen_width;
+        let y = self.v[y as usize] as usize % screen_height;
+        // the rows of the sprite with a collision on any plane, a bit each
+        let mut collided_rows = 0u16;
+        let mut sprite = self.i as usize;
+        for plane in [0x1u8, 0x2] {
+            if self.planes & plane == 0 {
+                continue;
+            }
+            for yline in 0..height {
+                let mut y_cord = y + yline;
+                if y_cord >= screen_height {
+                    if self.quirks.clip_sprites {
+                        continue;
+                    }
+                    y_cord %= screen_height;
+                }
+                let row = sprite + yline * bytes_per_row;
+                let mut pixels = 0;
+                let mut collided = false;
+                for xline in 0..width {
+                    // each byte of the sprite is read once
+                    if xline.is_multiple_of(8) {
+                        pixels = self.read_byte(row + xline / 8, Access::Read)?;
+                    }
+                    if (pixels & (0x80 >> (xline % 8))) != 0 {
+                        // determine for each byte if it is on
+                        let mut x_cord: usize = x + xline;
+                        if x_cord >= screen_width {
+                            if self.quirks.clip_sprites {
+                                continue;
+                            }
+                            x_cord %= screen_width;
+                        }
+                        // detect coliision
+                        let current = self.display.get(x_cord, y_cord);
+                        if current & plane != 0 {
+                            collided = true;
+                        }
+                        self.display.set(x_cord, y_cord, current ^ plane);
                     }
-                    self.display[y_cord][x_cord] ^= 1;
                 }
+                collided_rows |= (collided as u16) << yline;
             }
+            sprite += bytes_per_row * height;
         }
-        self.pc += 2;
-        self.draw = true;
+        // the rows clipped at the bottom, whatever the planes
+        let clipped_rows = if self.quirks.clip_sprites {
+            (y + height).saturating_sub(screen_height)
+        } else {
+            0
+        };
+        // written last, vf can be one of the coordinates
+        self.v[0xF] = if self.quirks.collision_counts_rows && screen_width == HIRES_WIDTH {
+            (collided_rows.count_ones() as usize + clipped_rows) as u8
+        } else {
+            (collided_rows != 0) as u8
+        };
+        Ok(())
     }
 
-    // skip next instruction if keyboard at x is pressed
+    // skip next instruction if the key in vx is pressed
     fn op_ex9e(&mut self, x: u8) {
-        if self.keyboard[x as usize] {
-            self.pc += 4;
+        if self.key_active(self.v[x as usize] as usize & 0xF) {
+            self.skip_next();
         } else {
-            self.pc += 2;
+            self.pc = self.pc.wrapping_add(2);
         }
     }
 
-    // skip next instruction if keyboard at x is not pressed
+    // skip next instruction if the key in vx is not pressed
     fn op_exa1(&mut self, x: u8) {
-        if !self.keyboard[x as usize] {
-            self.pc += 4;
+        if !self.key_active(self.v[x as usize] as usize & 0xF) {
+            self.skip_next();
         } else {
-            self.pc += 2;
+            self.pc = self.pc.wrapping_add(2);
         }
     }
 
+    // load I with the 16 bit address stored after the instruction (a 4 bytes instruction)
+    fn op_f000(&mut self) -> Result<(), Chip8Error> {
+        self.i = self.read_word(self.pc as usize + 2, Access::Fetch)?;
+        self.pc = self.pc.wrapping_add(4);
+        Ok(())
+    }
+
+    // select the planes used for drawing, scrolling and clearing (n is a bitmask)
+    fn op_fn01(&mut self, n: u8) {
+        self.planes = n & 0x3;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // load 16 bytes from I to the audio pattern buffer
+    fn op_f002(&mut self) -> Result<(), Chip8Error> {
+        let pointer = self.i as usize;
+        for offset in 0..AUDIO_PATTERN_SIZE {
+            self.audio_pattern[offset] = self.read_byte(pointer + offset, Access::Read)?;
+        }
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
+    }
+
+    // set the audio pattern pitch to vx
+    fn op_fx3a(&mut self, x: u8) {
+        self.pitch = self.v[x as usize];
+        self.pc = self.pc.wrapping_add(2);
+    }
+
     // set vx to delay timer value
     fn op_fx07(&mut self, x: u8) {
         self.v[x as usize] = self.delay_timer;
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
 
-    // wait for keypress, store result is vx
+    // wait for a key, store it in vx
     fn op_fx0a(&mut self, x: u8) {
-        for i in 0x0..=0xF {
-            if self.keyboard[i] {
-                self.v[x as usize] = i as u8;
-                self.pc += 2;
+        // pc stays on fx0a until a key is stored, the timers keep running meanwhile
+        let key = match self.key_wait {
+            Some(key) if self.keyboard[key as usize] => None,
+            Some(key) => Some(key),
+            None => {
+                // the lowest key pressed, the others are ignored. A tap is only taken once
+                let pressed = (0..NUM_KEYS).find(|&key| self.key_active(key));
+                if let Some(key) = pressed {
+                    self.pressed_keys &= !(1 << key);
+                }
+                self.key_wait = pressed.map(|key| key as u8);
+                if self.quirks.key_wait_on_release {
+                    None
+                } else {
+                    self.key_wait
+                }
+            }
+        };
+        match key {
+            Some(key) => {
+                self.key_wait = None;
+                self.v[x as usize] = key;
+                self.pc = self.pc.wrapping_add(2);
+            }
+            None if self.key_wait.is_some() && self.quirks.key_wait_beep => {
+                self.sound_timer = self.sound_timer.max(KEY_WAIT_BEEP);
             }
+            None => {}
         }
     }
 
     // set delay timer to vx
     fn op_fx15(&mut self, x: u8) {
         self.delay_timer = self.v[x as usize];
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
 
     // Set sound timer = Vx.
     fn op_fx18(&mut self, x: u8) {
         self.sound_timer = self.v[x as usize];
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
 
     // i + vx are added, stored in i//TODO: what?
     fn op_fx1e(&mut self, x: u8) {
-        self.i += self.v[x as usize] as u16;
+        self.i = self.i.wrapping_add(self.v[x as usize] as u16);
         //self.v[0x0f] = if self.i > 0x0F00 { 1 } else { 0 };
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
 
     // Set I = location of sprite for digit Vx.
     fn op_fx29(&mut self, x: u8) {
-        self.i = (self.v[x as usize] * 5) as u16;
-        self.pc += 2;
+        self.i = (self.v[x as usize] & 0xF) as u16 * 5;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // Set I = location of the big (8x10) sprite for digit Vx.
+    fn op_fx30(&mut self, x: u8) {
+        self.i = (BIG_FONT_ADDRESS + (self.v[x as usize] & 0xF) as usize * 10) as u16;
+        self.pc = self.pc.wrapping_add(2);
     }
 
     // Store BCD representation of Vx in memory locations I, I+1, and I+2.
-    fn op_fx33(&mut self, x: u8) {
+    fn op_fx33(&mut self, x: u8) -> Result<(), Chip8Error> {
         let value = self.v[x as usize];
-        self.memory[self.i as usize] = value / 100;
-        self.memory[(self.i + 1) as usize] = (value / 10) % 10;
-        self.memory[(self.i + 2) as usize] = value % 10;
-        self.pc += 2;
+        let pointer = self.i as usize;
+        self.write_byte(pointer, value / 100)?;
+        self.write_byte(pointer + 1, (value / 10) % 10)?;
+        self.write_byte(pointer + 2, value % 10)?;
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
     }
 
     // Store registers V0 through Vx in memory starting at location I.
-    fn op_fx55(&mut self, x: u8) {
-        let mut pointer: usize = self.i as usize;
-        for register in 0x0..x + 1 {
-            self.memory[pointer] = self.v[register as usize];
-            pointer += 1;
+    fn op_fx55(&mut self, x: u8) -> Result<(), Chip8Error> {
+        let pointer: usize = self.i as usize;
+        for register in 0..=x as usize {
+            self.write_byte(pointer + register, self.v[register])?;
         }
-        self.pc += 2;
+        if self.quirks.load_store_increments_i {
+            self.i = self.i.wrapping_add(x as u16 + 1);
+        }
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
     }
 
     // Read registers V0 through Vx from memory starting at location I.
-    fn op_fx65(&mut self, x: u8) {
-        let mut pointer: usize = self.i as usize;
-        for register in 0x0..x + 1 {
-            self.v[register as usize] = self.memory[pointer];
-            pointer += 1;
+    fn op_fx65(&mut self, x: u8) -> Result<(), Chip8Error> {
+        let pointer: usize = self.i as usize;
+        for register in 0..=x as usize {
+            self.v[register] = self.read_byte(pointer + register, Access::Read)?;
+        }
+        if self.quirks.load_store_increments_i {
+            self.i = self.i.wrapping_add(x as u16 + 1);
         }
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
+    }
+
+    // Store V0 through Vx in the RPL user flags (x <= 7)
+    fn op_fx75(&mut self, x: u8) {
+        let count = (x as usize + 1).min(NUM_RPL_FLAGS);
+        self.rpl[..count].copy_from_slice(&self.v[..count]);
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // Read V0 through Vx from the RPL user flags (x <= 7)
+    fn op_fx85(&mut self, x: u8) {
+        let count = (x as usize + 1).min(NUM_RPL_FLAGS);
+        self.v[..count].copy_from_slice(&self.rpl[..count]);
+        self.pc = self.pc.wrapping_add(2);
     }
 }
 
+#[path = "./savestate.rs"]
+mod savestate;
+
 #[cfg(test)]
 #[path = "./opcode_tests.rs"]
 mod opcode_tests;
@@ -0,0 +1,414 @@
+// The configuration file: which keyboard keys are the CHIP-8 keypad, and which ones run the
+// emulator functions. The keys are named independently of the frontend.
+//
+//     # comments are lines starting with # or ;
+//     [keys]                  the keypad: CHIP-8 key = keyboard keys
+//     5 = W, Up
+//     [hotkeys]               emulator function = keyboard keys
+//     pause = Space
+//     [display]               how the screen looks
+//     palette = amber         a preset or custom colors, see palette.rs
+//     scaling = fit           integer (by default) or fit, see viewport.rs
+//     scale = 10              the size of the CHIP-8 pixels in the window when it opens
+//     border = #202020        the color around the screen, its background color by default
+//     flicker = decay         off (by default), decay or or, see persistence.rs
+//     flicker_strength = 0.8  0 - 1, a default for each mode when not set
+//     [rom.0123456789ABCDEF]  the keypad of the ROM with this hash (movie::hash), replacing
+//     5 = I                   the [keys] bindings of the CHIP-8 keys it lists
+//
+// A line replaces all the default bindings of its CHIP-8 key or function, an empty value unbinds
+// it. A keyboard key can't be bound twice.
+use crate::chip8::NUM_KEYS;
+use crate::palette::{self, Palette, Rgb};
+use crate::persistence::Flicker;
+use crate::viewport::Scaling;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+
+// Emulator functions bound to keys outside of the CHIP-8 keypad
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Hotkey {
+    Quit,
+    // stop and resume the machine
+    Pause,
+    // restart the ROM
+    Reset,
+    // save to the current slot
+    SaveState,
+    // load the current slot
+    LoadState,
+    // select the previous/next slot
+    PreviousSlot,
+    NextSlot,
+    // (held) run the game backwards
+    Rewind,
+    // double/halve the instructions per frame
+    SpeedUp,
+    SpeedDown,
+    // switch to the next palette preset
+    NextPalette,
+    // write the screen as a PNG image
+    Screenshot,
+    // switch between the window and fullscreen
+    Fullscreen,
+}
+
+// the hotkeys by name, with their default keys
+const HOTKEYS: [(&str, Hotkey, &str); 13] = [
+    ("quit", Hotkey::Quit, "Escape"),
+    ("pause", Hotkey::Pause, "F9"),
+    ("reset", Hotkey::Reset, "F2"),
+    ("save_state", Hotkey::SaveState, "F5"),
+    ("load_state", Hotkey::LoadState, "F8"),
+    ("previous_slot", Hotkey::PreviousSlot, "F6"),
+    ("next_slot", Hotkey::NextSlot, "F7"),
+    ("rewind", Hotkey::Rewind, "Backspace"),
+    ("speed_up", Hotkey::SpeedUp, "F4"),
+    ("speed_down", Hotkey::SpeedDown, "F3"),
+    ("next_palette", Hotkey::NextPalette, "F10"),
+    ("screenshot", Hotkey::Screenshot, "F12"),
+    ("fullscreen", Hotkey::Fullscreen, "F11"),
+];
+
+// the keypad on the left of a QWERTY keyboard, CHIP-8 keys 0 - F
+const DEFAULT_KEYS: [&str; NUM_KEYS] = [
+    "X", "1", "2", "3", "Q", "W", "E", "A", "S", "D", "Z", "C", "4", "R", "F", "V",
+];
+
+// the keys that aren't a letter, a digit or F1 - F12
+pub const NAMED_KEYS: [&str; 27] = [
+    "Escape",
+    "Backspace",
+    "Tab",
+    "Return",
+    "Space",
+    "Up",
+    "Down",
+    "Left",
+    "Right",
+    "Home",
+    "End",
+    "PageUp",
+    "PageDown",
+    "Insert",
+    "Delete",
+    "Pause",
+    "Minus",
+    "Equals",
+    "Comma",
+    "Period",
+    "Semicolon",
+    "Quote",
+    "Slash",
+    "Backslash",
+    "LeftBracket",
+    "RightBracket",
+    "Backquote",
+];
+
+#[derive(Debug)]
+pub enum ConfigError {
+    // the file couldn't be read
+    Io(io::Error),
+    // a mistake in the file, lines start at 1
+    Invalid { line: usize, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "{}", e),
+            ConfigError::Invalid { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> ConfigError {
+        ConfigError::Io(e)
+    }
+}
+
+// What a keyboard key does
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    // a key of the CHIP-8 keypad
+    Key(u8),
+    Hotkey(Hotkey),
+}
+
+// the keyboard keys of a CHIP-8 key or a hotkey, and the line they were set on (0 by default)
+type Bindings = HashMap<Binding, (Vec<String>, usize)>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    // the keypad and the hotkeys
+    bindings: Bindings,
+    // the keypad overrides by ROM hash
+    roms: HashMap<u64, Bindings>,
+    // the [display] settings, the frontend's defaults when not set
+    pub palette: Option<Palette>,
+    pub scaling: Option<Scaling>,
+    pub scale: Option<u32>,
+    pub border: Option<Rgb>,
+    pub flicker: Option<Flicker>,
+    pub flicker_strength: Option<f32>,
+}
+
+// the section of the line being parsed
+#[derive(Clone, Copy)]
+enum Section {
+    Keys,
+    Hotkeys,
+    Display,
+    Rom(u64),
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        let mut bindings = HashMap::new();
+        for (key, name) in DEFAULT_KEYS.iter().enumerate() {
+            bindings.insert(Binding::Key(key as u8), (vec![name.to_string()], 0));
+        }
+        for &(_, hotkey, name) in HOTKEYS.iter() {
+            bindings.insert(Binding::Hotkey(hotkey), (vec![name.to_string()], 0));
+        }
+        Config {
+            bindings,
+            roms: HashMap::new(),
+            palette: None,
+            scaling: None,
+            scale: None,
+            border: None,
+            flicker: None,
+            flicker_strength: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Config, ConfigError> {
+        Config::parse(&fs::read_to_string(path)?)
+    }
+
+    // The defaults, changed by the lines of text
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        let mut section = None;
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let invalid = |message: String| ConfigError::Invalid {
+                line: line_number,
+                message,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(['#', ';']) {
+                continue;
+            }
+
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|name| name.strip_suffix(']'))
+            {
+                let name = name.trim();
+                section = Some(match name {
+                    "keys" => Section::Keys,
+                    "hotkeys" => Section::Hotkeys,
+                    "display" => Section::Display,
+                    _ => match name.strip_prefix("rom.") {
+                        Some(hash) => {
+                            let hash = rom_hash(hash).ok_or_else(|| {
+                                invalid(format!("Invalid ROM hash (16 hex digits): {}", hash))
+                            })?;
+                            config.roms.entry(hash).or_default();
+                            Section::Rom(hash)
+                        }
+                        None => return Err(invalid(format!("Unknown section: [{}]", name))),
+                    },
+                });
+                continue;
+            }
+
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("Expected name = value, found {}", line)))?;
+            let (name, value) = (name.trim(), value.trim());
+            let section = section.ok_or_else(|| invalid("Expected a section first".to_string()))?;
+            let binding = match section {
+                Section::Display => {
+                    let bad_value = || invalid(format!("Invalid {}: {}", name, value));
+                    match name {
+                        "palette" => config.palette = Some(Palette::parse(value).map_err(invalid)?),
+                        "scaling" => {
+                            config.scaling = Some(Scaling::parse(value).ok_or_else(bad_value)?)
+                        }
+                        "scale" => match value.parse() {
+                            Ok(scale) if scale > 0 => config.scale = Some(scale),
+                            _ => return Err(bad_value()),
+                        },
+                        "border" => {
+                            config.border = Some(palette::parse_color(value).ok_or_else(bad_value)?)
+                        }
+                        "flicker" => {
+                            config.flicker = Some(Flicker::parse(value).ok_or_else(bad_value)?)
+                        }
+                        "flicker_strength" => match value.parse() {
+                            Ok(strength) if (0.0..=1.0).contains(&strength) => {
+                                config.flicker_strength = Some(strength)
+                            }
+                            _ => return Err(bad_value()),
+                        },
+                        _ => return Err(invalid(format!("Unknown display setting: {}", name))),
+                    }
+                    continue;
+                }
+                Section::Hotkeys => HOTKEYS
+                    .iter()
+                    .find(|(hotkey, _, _)| hotkey.eq_ignore_ascii_case(name))
+                    .map(|&(_, hotkey, _)| Binding::Hotkey(hotkey))
+                    .ok_or_else(|| invalid(format!("Unknown hotkey: {}", name)))?,
+                Section::Keys | Section::Rom(_) => chip8_key(name)
+                    .map(Binding::Key)
+                    .ok_or_else(|| invalid(format!("Not a CHIP-8 key (0 - F): {}", name)))?,
+            };
+            let mut keys = Vec::new();
+            for key in value
+                .split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+            {
+                keys.push(key_name(key).ok_or_else(|| invalid(format!("Unknown key: {}", key)))?);
+            }
+
+            let bindings = match section {
+                Section::Rom(hash) => config.roms.entry(hash).or_default(),
+                _ => &mut config.bindings,
+            };
+            bindings.insert(binding, (keys, line_number));
+        }
+
+        config.check(&config.bindings)?;
+        for overrides in config.roms.values() {
+            config.check(&config.merge(overrides))?;
+        }
+        Ok(config)
+    }
+
+    // The keyboard keys and what they do for the ROM with this hash
+    pub fn bindings(&self, rom_hash: u64) -> Vec<(String, Binding)> {
+        let bindings = match self.roms.get(&rom_hash) {
+            Some(overrides) => self.merge(overrides),
+            None => self.bindings.clone(),
+        };
+        let mut list: Vec<(String, Binding)> = bindings
+            .into_iter()
+            .flat_map(|(binding, (keys, _))| keys.into_iter().map(move |key| (key, binding)))
+            .collect();
+        list.sort_by(|a, b| a.0.cmp(&b.0));
+        list
+    }
+
+    fn merge(&self, overrides: &Bindings) -> Bindings {
+        let mut bindings = self.bindings.clone();
+        bindings.extend(
+            overrides
+                .iter()
+                .map(|(&binding, keys)| (binding, keys.clone())),
+        );
+        bindings
+    }
+
+    // a keyboard key bound twice is an error
+    fn check(&self, bindings: &Bindings) -> Result<(), ConfigError> {
+        let mut seen: HashMap<&str, (Binding, usize)> = HashMap::new();
+        // the lines in order, so that the error is on the second binding
+        let mut sorted: Vec<(&Binding, &(Vec<String>, usize))> = bindings.iter().collect();
+        sorted.sort_by_key(|(binding, (_, line))| (*line, describe(**binding)));
+        for (&binding, (keys, line)) in sorted {
+            for key in keys {
+                if let Some(&(other, other_line)) = seen.get(key.as_str()) {
+                    let other = match other_line {
+                        0 => format!("{} (by default)", describe(other)),
+                        _ => format!("{} (line {})", describe(other), other_line),
+                    };
+                    return Err(ConfigError::Invalid {
+                        line: (*line).max(other_line),
+                        message: format!(
+                            "{} is bound to both {} and {}",
+                            key,
+                            other,
+                            describe(binding)
+                        ),
+                    });
+                }
+                seen.insert(key, (binding, *line));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn describe(binding: Binding) -> String {
+    match binding {
+        Binding::Key(key) => format!("CHIP-8 key {:X}", key),
+        Binding::Hotkey(hotkey) => {
+            let name = HOTKEYS.iter().find(|(_, other, _)| *other == hotkey);
+            name.map_or("?", |(name, _, _)| name).to_string()
+        }
+    }
+}
+
+// the 16 hexadecimal digits of [rom.<hash>], as the emulator prints them
+fn rom_hash(digits: &str) -> Option<u64> {
+    if digits.len() != 16 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u64::from_str_radix(digits, 16).ok()
+}
+
+// 0 - F
+fn chip8_key(name: &str) -> Option<u8> {
+    match name.len() {
+        1 => u8::from_str_radix(name, 16).ok(),
+        _ => None,
+    }
+}
+
+// The spelling of a keyboard key name: a letter, a digit, F1 - F12, Keypad0 - Keypad9 or one of
+// NAMED_KEYS, in any case
+pub fn key_name(name: &str) -> Option<String> {
+    let upper = name.to_ascii_uppercase();
+    if upper.len() == 1 && upper.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Some(upper);
+    }
+    if let Some(n) = upper.strip_prefix('F').and_then(|n| n.parse::<u8>().ok()) {
+        if (1..=12).contains(&n) && !upper.starts_with("F0") {
+            return Some(format!("F{}", n));
+        }
+    }
+    if let Some(digit) = upper.strip_prefix("KEYPAD") {
+        if digit.len() == 1 && digit.chars().all(|c| c.is_ascii_digit()) {
+            return Some(format!("Keypad{}", digit));
+        }
+    }
+    NAMED_KEYS
+        .iter()
+        .find(|key| key.eq_ignore_ascii_case(name))
+        .map(|key| key.to_string())
+}
+
+#[cfg(test)]
+#[path = "./config_tests.rs"]
+mod config_tests;
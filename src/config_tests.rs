@@ -0,0 +1,192 @@
+use super::*;
+
+const ROM: u64 = 0x0123_4567_89AB_CDEF;
+
+fn keys_of(config: &Config, rom: u64, binding: Binding) -> Vec<String> {
+    config
+        .bindings(rom)
+        .into_iter()
+        .filter(|&(_, other)| other == binding)
+        .map(|(key, _)| key)
+        .collect()
+}
+
+fn error_line(text: &str) -> (usize, String) {
+    match Config::parse(text) {
+        Err(ConfigError::Invalid { line, message }) => (line, message),
+        other => panic!("expected an error, got {:?}", other),
+    }
+}
+
+#[test]
+fn defaults() {
+    let config = Config::parse("").unwrap();
+    assert_eq!(config, Config::default());
+    assert_eq!(keys_of(&config, 0, Binding::Key(0x0)), ["X"]);
+    assert_eq!(keys_of(&config, 0, Binding::Key(0xC)), ["4"]);
+    assert_eq!(
+        keys_of(&config, 0, Binding::Hotkey(Hotkey::Quit)),
+        ["Escape"]
+    );
+    assert_eq!(config.bindings(0).len(), NUM_KEYS + HOTKEYS.len());
+}
+
+#[test]
+fn overrides() {
+    let config = Config::parse(
+        "# arrows for movement
+        [keys]
+        ; both
+        5 = w, Up
+        8 = S, down
+        [hotkeys]
+        pause = space
+        rewind =",
+    )
+    .unwrap();
+    assert_eq!(keys_of(&config, 0, Binding::Key(0x5)), ["Up", "W"]);
+    assert_eq!(keys_of(&config, 0, Binding::Key(0x8)), ["Down", "S"]);
+    assert_eq!(keys_of(&config, 0, Binding::Key(0x7)), ["A"]);
+    assert_eq!(
+        keys_of(&config, 0, Binding::Hotkey(Hotkey::Pause)),
+        ["Space"]
+    );
+    assert!(keys_of(&config, 0, Binding::Hotkey(Hotkey::Rewind)).is_empty());
+}
+
+#[test]
+fn per_rom_keys() {
+    let config = Config::parse(
+        "[keys]
+        5 = Up
+        [rom.0123456789abcdef]
+        5 = I
+        6 = Up",
+    )
+    .unwrap();
+    assert_eq!(keys_of(&config, ROM, Binding::Key(0x5)), ["I"]);
+    assert_eq!(keys_of(&config, ROM, Binding::Key(0x6)), ["Up"]);
+    assert_eq!(keys_of(&config, ROM, Binding::Key(0x4)), ["Q"]);
+    // other ROMs keep the global keypad
+    assert_eq!(keys_of(&config, 1, Binding::Key(0x5)), ["Up"]);
+    assert_eq!(keys_of(&config, 1, Binding::Key(0x6)), ["E"]);
+}
+
+#[test]
+fn key_names() {
+    assert_eq!(key_name("q").as_deref(), Some("Q"));
+    assert_eq!(key_name("7").as_deref(), Some("7"));
+    assert_eq!(key_name("f12").as_deref(), Some("F12"));
+    assert_eq!(key_name("keypad5").as_deref(), Some("Keypad5"));
+    assert_eq!(key_name("PAGEUP").as_deref(), Some("PageUp"));
+    assert_eq!(key_name("F13"), None);
+    assert_eq!(key_name("F01"), None);
+    assert_eq!(key_name("Keypad"), None);
+    assert_eq!(key_name("Meta"), None);
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        error_line("5 = W"),
+        (1, "Expected a section first".to_string())
+    );
+    assert_eq!(
+        error_line("\n[keypad]"),
+        (2, "Unknown section: [keypad]".to_string())
+    );
+    assert_eq!(
+        error_line("[rom.1234]"),
+        (1, "Invalid ROM hash (16 hex digits): 1234".to_string())
+    );
+    assert_eq!(
+        error_line("[keys]\n\nG = W"),
+        (3, "Not a CHIP-8 key (0 - F): G".to_string())
+    );
+    assert_eq!(
+        error_line("[keys]\n5 = Meta"),
+        (2, "Unknown key: Meta".to_string())
+    );
+    assert_eq!(
+        error_line("[hotkeys]\nturbo = T"),
+        (2, "Unknown hotkey: turbo".to_string())
+    );
+    assert_eq!(
+        error_line("[keys]\n5"),
+        (2, "Expected name = value, found 5".to_string())
+    );
+}
+
+#[test]
+fn key_bound_twice() {
+    assert_eq!(
+        error_line("[keys]\n5 = Up\n8 = Up"),
+        (
+            3,
+            "Up is bound to both CHIP-8 key 5 (line 2) and CHIP-8 key 8".to_string()
+        )
+    );
+    // against a default binding
+    assert_eq!(
+        error_line("[hotkeys]\npause = Escape"),
+        (
+            2,
+            "Escape is bound to both quit (by default) and pause".to_string()
+        )
+    );
+    // the overrides of a ROM are checked against the global keypad
+    assert_eq!(
+        error_line("[rom.0123456789ABCDEF]\n5 = Q"),
+        (
+            2,
+            "Q is bound to both CHIP-8 key 4 (by default) and CHIP-8 key 5".to_string()
+        )
+    );
+}
+
+#[test]
+fn display_palette() {
+    assert_eq!(Config::default().palette, None);
+    let config = Config::parse("[display]\npalette = amber").unwrap();
+    assert_eq!(config.palette, crate::palette::preset("amber"));
+    assert_eq!(
+        error_line("[display]\npalette = #000000 # black"),
+        (
+            2,
+            "Invalid color (6 hex digits): #000000 # black".to_string()
+        )
+    );
+    let config = Config::parse("[display]\npalette = #000000, #FF0000").unwrap();
+    assert_eq!(config.palette.unwrap().color(1), [255, 0, 0]);
+    assert_eq!(
+        error_line("[display]\nscanlines = on"),
+        (2, "Unknown display setting: scanlines".to_string())
+    );
+}
+
+#[test]
+fn display_scaling() {
+    let config = Config::parse("[display]\nscaling = fit\nscale = 8\nborder = 202020").unwrap();
+    assert_eq!(config.scaling, Some(Scaling::Fit));
+    assert_eq!(config.scale, Some(8));
+    assert_eq!(config.border, Some([0x20, 0x20, 0x20]));
+    assert_eq!(
+        error_line("[display]\nscaling = stretch"),
+        (2, "Invalid scaling: stretch".to_string())
+    );
+    assert_eq!(
+        error_line("[display]\nscale = 0"),
+        (2, "Invalid scale: 0".to_string())
+    );
+}
+
+#[test]
+fn display_flicker() {
+    let config = Config::parse("[display]\nflicker = or\nflicker_strength = 0.25").unwrap();
+    assert_eq!(config.flicker, Some(Flicker::Or));
+    assert_eq!(config.flicker_strength, Some(0.25));
+    assert_eq!(
+        error_line("[display]\nflicker_strength = 2"),
+        (2, "Invalid flicker_strength: 2".to_string())
+    );
+}
@@ -0,0 +1,573 @@
+// The debugger: breakpoints and stepping on top of the machine, driven by text commands.
+// It doesn't read or print anything itself, the frontend feeds it the lines typed and shows
+// what comes back
+use crate::chip8::{Access, Chip8, MemoryObserver, NUM_REGISTERS};
+use crate::disasm::disassemble;
+use crate::error::Chip8Error;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::rc::Rc;
+
+pub const HELP: &str = "Commands:
+    step [n]            execute n instructions (1 by default, 100000 at most)
+    continue            run until a breakpoint
+    pause               stop running
+    break [addr]        set a breakpoint at addr, or list the breakpoints
+    delete [addr]       remove the breakpoint at addr, or all of them
+    watch <addr> [n] [r|w|rw]
+                        stop when n bytes from addr are read or written (1 and rw by default)
+    watch <reg> <value> stop when a register takes value
+    watch               list the watchpoints
+    unwatch [n]         remove watchpoint n, or all of them
+    regs                show the registers
+    mem <addr> [len]    dump len bytes of memory (16 by default)
+    disasm [addr] [n]   disassemble n instructions from addr (pc and 10 by default)
+    set <reg> <value>   set v0 - vf, i, pc, dt or st
+    stack               show the call stack
+    timers              show the delay and sound timers
+    help                show this message
+    quit                exit the emulator
+Numbers are decimal or 0x prefixed hexadecimal. The timers don't run while paused";
+
+// the watched accesses listed when a watchpoint stops the program
+const MAX_HITS_SHOWN: usize = 4;
+// the instructions a step command runs at most, all of them between two frames
+const MAX_STEPS: u64 = 100_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Register {
+    V(usize),
+    I,
+    Pc,
+    DelayTimer,
+    SoundTimer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Watch {
+    // the memory from start to end (included)
+    Memory {
+        start: u16,
+        end: u16,
+        read: bool,
+        write: bool,
+    },
+    // a register taking a value
+    Register(Register, u16),
+}
+
+impl Watch {
+    fn parse(args: &[&str]) -> Result<Watch, String> {
+        let target = args.first().ok_or("Missing address or register")?;
+        if let Ok(register) = Command::register(target) {
+            let value = number(args.get(1).ok_or("Missing value")?)?;
+            return Ok(Watch::Register(register, fit(register, value)?));
+        }
+
+        let start = address(number(target)?)?;
+        let mut len = 1;
+        let (mut read, mut write) = (true, true);
+        for &arg in args[1..].iter() {
+            match arg {
+                "r" => write = false,
+                "w" => read = false,
+                "rw" => {}
+                _ => len = number(arg)?,
+            }
+        }
+        if len == 0 || start as u64 + len > 0x10000 {
+            return Err(format!("Invalid length: {}", len));
+        }
+        Ok(Watch::Memory {
+            start,
+            end: (start as u64 + len - 1) as u16,
+            read,
+            write,
+        })
+    }
+}
+
+impl fmt::Display for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Watch::Memory {
+                start,
+                end,
+                read,
+                write,
+            } => {
+                let access = match (read, write) {
+                    (true, false) => "reads of",
+                    (false, true) => "writes to",
+                    _ => "accesses to",
+                };
+                write!(f, "{} {:#06X} - {:#06X}", access, start, end)
+            }
+            Watch::Register(register, value) => write!(f, "{} = {:#X}", register, value),
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Register::V(x) => write!(f, "V{:X}", x),
+            Register::I => write!(f, "I"),
+            Register::Pc => write!(f, "PC"),
+            Register::DelayTimer => write!(f, "DT"),
+            Register::SoundTimer => write!(f, "ST"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Step(usize),
+    Continue,
+    Pause,
+    Break(Option<u16>),
+    Delete(Option<u16>),
+    Watch(Option<Watch>),
+    Unwatch(Option<usize>),
+    Regs,
+    Mem { addr: u16, len: usize },
+    Disasm { addr: Option<u16>, count: usize },
+    Set(Register, u16),
+    Stack,
+    Timers,
+    Help,
+    Quit,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let mut words = line.split_whitespace();
+        let name = words.next().ok_or("No command given")?;
+        let args: Vec<&str> = words.collect();
+        let arg = |index: usize| args.get(index).map(|&arg| number(arg)).transpose();
+        let command = match name {
+            "step" | "s" => match arg(0)?.unwrap_or(1) {
+                count if count > MAX_STEPS => {
+                    return Err(format!("Too many steps: {} (at most {})", count, MAX_STEPS))
+                }
+                count => Command::Step(count as usize),
+            },
+            "continue" | "c" => Command::Continue,
+            "pause" | "p" => Command::Pause,
+            "break" | "b" => Command::Break(arg(0)?.map(address).transpose()?),
+            "delete" | "d" => Command::Delete(arg(0)?.map(address).transpose()?),
+            "watch" | "w" if args.is_empty() => Command::Watch(None),
+            "watch" | "w" => Command::Watch(Some(Watch::parse(&args)?)),
+            "unwatch" => Command::Unwatch(arg(0)?.map(|n| n as usize)),
+            "regs" | "r" => Command::Regs,
+            "mem" | "m" => Command::Mem {
+                addr: address(arg(0)?.ok_or("Missing address")?)?,
+                len: arg(1)?.unwrap_or(16) as usize,
+            },
+            "disasm" | "u" => Command::Disasm {
+                addr: arg(0)?.map(address).transpose()?,
+                count: arg(1)?.unwrap_or(10) as usize,
+            },
+            "set" => {
+                let register = args.first().ok_or("Missing register")?;
+                let register = Command::register(register)?;
+                let value = arg(1)?.ok_or("Missing value")?;
+                Command::Set(register, fit(register, value)?)
+            }
+            "stack" => Command::Stack,
+            "timers" => Command::Timers,
+            "help" | "h" => Command::Help,
+            "quit" | "q" => Command::Quit,
+            _ => return Err(format!("Unknown command: {}", name)),
+        };
+        Ok(command)
+    }
+
+    fn register(name: &str) -> Result<Register, String> {
+        let lower = name.to_lowercase();
+        let register = match lower.as_str() {
+            "i" => Register::I,
+            "pc" => Register::Pc,
+            "dt" => Register::DelayTimer,
+            "st" => Register::SoundTimer,
+            _ => match lower
+                .strip_prefix('v')
+                .map(|x| usize::from_str_radix(x, 16))
+            {
+                Some(Ok(x)) if x < NUM_REGISTERS => Register::V(x),
+                _ => return Err(format!("Unknown register: {}", name)),
+            },
+        };
+        Ok(register)
+    }
+}
+
+// A decimal or 0x prefixed hexadecimal number, as the commands and the command line take them
+pub fn parse_number(value: &str) -> Option<u64> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+fn number(value: &str) -> Result<u64, String> {
+    parse_number(value).ok_or_else(|| format!("Invalid number: {}", value))
+}
+
+// check that the value fits in the register
+fn fit(register: Register, value: u64) -> Result<u16, String> {
+    let max = match register {
+        Register::I | Register::Pc => 0xFFFF,
+        _ => 0xFF,
+    };
+    if value > max {
+        return Err(format!("{:#X} doesn't fit in {}", value, register));
+    }
+    Ok(value as u16)
+}
+
+fn address(value: u64) -> Result<u16, String> {
+    if value > 0xFFFF {
+        return Err(format!("Invalid address: {:#X}", value));
+    }
+    Ok(value as u16)
+}
+
+// The observer installed on the machine while there are memory watchpoints.
+// It notes the accesses hitting them, the debugger stops after the instruction that made them
+struct Watcher {
+    watches: Vec<Watch>,
+    hits: Rc<RefCell<Vec<String>>>,
+}
+
+impl MemoryObserver for Watcher {
+    fn on_access(&mut self, addr: usize, access: Access, value: u8) {
+        // breakpoints are for the fetches
+        let hit = self.watches.iter().any(|watch| match *watch {
+            Watch::Memory {
+                start,
+                end,
+                read,
+                write,
+            } => {
+                (start as usize..=end as usize).contains(&addr)
+                    && match access {
+                        Access::Fetch => false,
+                        Access::Read => read,
+                        Access::Write => write,
+                    }
+            }
+            Watch::Register(..) => false,
+        });
+        if hit {
+            let verb = if access == Access::Write {
+                "wrote"
+            } else {
+                "read"
+            };
+            let hit = format!("{} {:#04X} at {:#06X}", verb, value, addr);
+            self.hits.borrow_mut().push(hit);
+        }
+    }
+}
+
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    watches: Vec<Watch>,
+    // the watched memory accesses of the instruction being executed
+    hits: Rc<RefCell<Vec<String>>>,
+    paused: bool,
+    // set when running again, so that the breakpoint at pc doesn't stop it right away
+    resuming: bool,
+}
+
+impl Default for Debugger {
+    fn default() -> Debugger {
+        Debugger::new()
+    }
+}
+
+impl Debugger {
+    // the debugger starts paused, so breakpoints can be set before the program runs
+    pub fn new() -> Debugger {
+        Debugger {
+            breakpoints: BTreeSet::new(),
+            watches: Vec::new(),
+            hits: Rc::new(RefCell::new(Vec::new())),
+            paused: true,
+            resuming: false,
+        }
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    // Run a command, returns the text to show
+    pub fn execute(&mut self, command: &Command, chip8: &mut Chip8) -> String {
+        match *command {
+            Command::Step(count) => {
+                self.paused = true;
+                self.resuming = true;
+                for _ in 0..count {
+                    chip8.set_vblank();
+                    if let Some(stop) = self.step(chip8) {
+                        return stop;
+                    }
+                }
+                current(chip8)
+            }
+            Command::Continue => {
+                self.paused = false;
+                self.resuming = true;
+                "Running".to_string()
+            }
+            Command::Pause => {
+                self.paused = true;
+                current(chip8)
+            }
+            Command::Break(Some(addr)) => {
+                self.breakpoints.insert(addr);
+                format!("Breakpoint at {:#06X}", addr)
+            }
+            Command::Break(None) if self.breakpoints.is_empty() => "No breakpoints".to_string(),
+            Command::Break(None) => self
+                .breakpoints
+                .iter()
+                .map(|addr| format!("{:#06X}", addr))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Command::Delete(Some(addr)) => {
+                if self.breakpoints.remove(&addr) {
+                    format!("Deleted the breakpoint at {:#06X}", addr)
+                } else {
+                    format!("No breakpoint at {:#06X}", addr)
+                }
+            }
+            Command::Delete(None) => {
+                self.breakpoints.clear();
+                "Deleted all the breakpoints".to_string()
+            }
+            Command::Watch(Some(watch)) => {
+                self.watches.push(watch);
+                self.update_observer(chip8);
+                format!("Watchpoint {}: {}", self.watches.len(), watch)
+            }
+            Command::Watch(None) if self.watches.is_empty() => "No watchpoints".to_string(),
+            Command::Watch(None) => self
+                .watches
+                .iter()
+                .enumerate()
+                .map(|(index, watch)| format!("{}: {}", index + 1, watch))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Command::Unwatch(Some(n)) => {
+                if n == 0 || n > self.watches.len() {
+                    return format!("No watchpoint {}", n);
+                }
+                let watch = self.watches.remove(n - 1);
+                self.update_observer(chip8);
+                format!("Deleted watchpoint {}: {}", n, watch)
+            }
+            Command::Unwatch(None) => {
+                self.watches.clear();
+                self.update_observer(chip8);
+                "Deleted all the watchpoints".to_string()
+            }
+            Command::Regs => registers(chip8),
+            Command::Mem { addr, len } => dump(chip8.memory(), addr as usize, len),
+            Command::Disasm { addr, count } => {
+                let mut addr = addr.unwrap_or_else(|| chip8.pc()) as usize;
+                let mut lines = Vec::new();
+                for _ in 0..count {
+                    if addr >= chip8.memory().len() {
+                        break;
+                    }
+                    let (line, size) = disassemble(chip8.memory(), addr, chip8.mode());
+                    lines.push(format!("{:#06X}  {}", addr, line));
+                    addr += size;
+                }
+                lines.join("\n")
+            }
+            Command::Set(register, value) => {
+                set_register(chip8, register, value);
+                registers(chip8)
+            }
+            Command::Stack => {
+                let stack = &chip8.stack()[..chip8.sp() as usize];
+                if stack.is_empty() {
+                    return "The stack is empty".to_string();
+                }
+                stack
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(depth, addr)| format!("{:2}: {:#06X}", depth, addr))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            Command::Timers => format!("DT {}  ST {}", chip8.delay_timer(), chip8.sound_timer()),
+            Command::Help => HELP.to_string(),
+            // the frontend is the one exiting
+            Command::Quit => String::new(),
+        }
+    }
+
+    // Run a frame worth of instructions unless paused. Returns the reason it paused, if it did.
+    // The timers only count down when the whole frame ran
+    pub fn run_frame(&mut self, chip8: &mut Chip8, ipf: usize) -> Option<String> {
+        if self.paused {
+            return None;
+        }
+        for _ in 0..ipf {
+            if chip8.exited() {
+                break;
+            }
+            if let Some(stop) = self.step(chip8) {
+                self.paused = true;
+                return Some(stop);
+            }
+        }
+        chip8.handle_timers();
+        None
+    }
+
+    // Execute an instruction, unless a breakpoint is hit first. Returns why it stopped
+    fn step(&mut self, chip8: &mut Chip8) -> Option<String> {
+        let pc = chip8.pc();
+        if !std::mem::replace(&mut self.resuming, false) && self.breakpoints.contains(&pc) {
+            return Some(format!("Breakpoint\n{}", current(chip8)));
+        }
+        let before: Vec<u16> = self
+            .watches
+            .iter()
+            .map(|watch| match *watch {
+                Watch::Register(register, _) => register_value(chip8, register),
+                Watch::Memory { .. } => 0,
+            })
+            .collect();
+        if let Err(e) = chip8.step() {
+            self.hits.borrow_mut().clear();
+            return Some(error(e, chip8));
+        }
+
+        let mut hits = std::mem::take(&mut *self.hits.borrow_mut());
+        for (watch, before) in self.watches.iter().zip(before) {
+            if let Watch::Register(register, value) = *watch {
+                let after = register_value(chip8, register);
+                if after == value && before != value {
+                    hits.push(format!("{} = {:#X}", register, value));
+                }
+            }
+        }
+        if hits.is_empty() {
+            return None;
+        }
+        // a sprite can be up to 64 bytes
+        if hits.len() > MAX_HITS_SHOWN {
+            let more = hits.len() - MAX_HITS_SHOWN;
+            hits.truncate(MAX_HITS_SHOWN);
+            hits.push(format!("{} more", more));
+        }
+        Some(format!(
+            "Watchpoint: {} by the instruction at {:#06X}\n{}",
+            hits.join(", "),
+            pc,
+            current(chip8)
+        ))
+    }
+
+    // observe the memory accesses only while some memory is watched
+    fn update_observer(&self, chip8: &mut Chip8) {
+        let watches: Vec<Watch> = self
+            .watches
+            .iter()
+            .filter(|watch| matches!(watch, Watch::Memory { .. }))
+            .copied()
+            .collect();
+        if watches.is_empty() {
+            chip8.set_observer(None);
+        } else {
+            chip8.set_observer(Some(Box::new(Watcher {
+                watches,
+                hits: Rc::clone(&self.hits),
+            })));
+        }
+    }
+}
+
+fn register_value(chip8: &Chip8, register: Register) -> u16 {
+    match register {
+        Register::V(x) => chip8.v()[x] as u16,
+        Register::I => chip8.i(),
+        Register::Pc => chip8.pc(),
+        Register::DelayTimer => chip8.delay_timer() as u16,
+        Register::SoundTimer => chip8.sound_timer() as u16,
+    }
+}
+
+fn set_register(chip8: &mut Chip8, register: Register, value: u16) {
+    match register {
+        Register::V(x) => chip8.set_v(x, value as u8),
+        Register::I => chip8.set_i(value),
+        Register::Pc => chip8.set_pc(value),
+        Register::DelayTimer => chip8.set_delay_timer(value as u8),
+        Register::SoundTimer => chip8.set_sound_timer(value as u8),
+    }
+}
+
+fn error(e: Chip8Error, chip8: &Chip8) -> String {
+    format!("Execution error: {}\n{}", e, current(chip8))
+}
+
+// the next instruction
+fn current(chip8: &Chip8) -> String {
+    let pc = chip8.pc() as usize;
+    let (line, _) = disassemble(chip8.memory(), pc, chip8.mode());
+    format!("{:#06X}  {}", pc, line)
+}
+
+fn registers(chip8: &Chip8) -> String {
+    let v = chip8.v();
+    let row = |start: usize| {
+        (start..start + NUM_REGISTERS / 2)
+            .map(|x| format!("V{:X} {:02X}", x, v[x]))
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+    format!(
+        "{}\n{}\nI {:#06X}  PC {:#06X}  SP {}",
+        row(0),
+        row(NUM_REGISTERS / 2),
+        chip8.i(),
+        chip8.pc(),
+        chip8.sp()
+    )
+}
+
+// 16 bytes per line
+fn dump(memory: &[u8], addr: usize, len: usize) -> String {
+    let end = addr.saturating_add(len).min(memory.len());
+    if addr >= end {
+        return format!("Nothing to show at {:#06X}", addr);
+    }
+    (addr..end)
+        .step_by(16)
+        .map(|line| {
+            let bytes: Vec<String> = memory[line..(line + 16).min(end)]
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect();
+            format!("{:#06X}  {}", line, bytes.join(" "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+#[path = "./debugger_tests.rs"]
+mod debugger_tests;
@@ -0,0 +1,240 @@
+use super::*;
+use crate::quirks::Quirks;
+
+// counts in v0 forever
+fn counter() -> Chip8 {
+    let mut instance = Chip8::new(Quirks::default());
+    instance
+        .load_rom_bytes(&[
+            0x70, 0x01, // 0x200: v0 += 1
+            0x12, 0x00, // 0x202: loop
+        ])
+        .unwrap();
+    instance
+}
+
+#[test]
+fn parse_commands() {
+    assert_eq!(Command::parse("step"), Ok(Command::Step(1)));
+    assert_eq!(Command::parse("  s 0x10 "), Ok(Command::Step(16)));
+    assert_eq!(
+        Command::parse("break 0x202"),
+        Ok(Command::Break(Some(0x202)))
+    );
+    assert_eq!(
+        Command::parse("mem 0x200 4"),
+        Ok(Command::Mem {
+            addr: 0x200,
+            len: 4
+        })
+    );
+    assert_eq!(
+        Command::parse("set v3 0x10"),
+        Ok(Command::Set(Register::V(3), 0x10))
+    );
+    assert_eq!(
+        Command::parse("set I 0x300"),
+        Ok(Command::Set(Register::I, 0x300))
+    );
+    assert!(Command::parse("set v3 0x100").is_err());
+    assert!(Command::parse("set vg 1").is_err());
+    assert!(Command::parse("break 0x10000").is_err());
+    assert!(Command::parse("mem").is_err());
+    assert!(Command::parse("step 100001").is_err());
+    assert!(Command::parse("jump").is_err());
+    assert!(Command::parse("").is_err());
+}
+
+#[test]
+fn starts_paused() {
+    let mut instance = counter();
+    let mut debugger = Debugger::new();
+    assert!(debugger.paused());
+    assert_eq!(debugger.run_frame(&mut instance, 10), None);
+    assert_eq!(instance.pc(), 0x200);
+}
+
+#[test]
+fn step_executes_instructions() {
+    let mut instance = counter();
+    let mut debugger = Debugger::new();
+    let output = debugger.execute(&Command::Step(3), &mut instance);
+    assert_eq!(output, "0x0202  JP 0x200");
+    assert_eq!(instance.v()[0], 2);
+    assert!(debugger.paused());
+}
+
+#[test]
+fn step_draws_without_waiting() {
+    let (_, quirks) = crate::quirks::profile("vip").unwrap();
+    let mut instance = Chip8::new(quirks);
+    instance
+        .load_rom_bytes(&[
+            0xD0, 0x05, // 0x200: draw
+            0xD0, 0x05, // 0x202: draw
+            0x12, 0x04, // 0x204: loop
+        ])
+        .unwrap();
+    let mut debugger = Debugger::new();
+    debugger.execute(&Command::Step(2), &mut instance);
+    assert_eq!(instance.pc(), 0x204);
+}
+
+#[test]
+fn breakpoint_stops_run() {
+    let mut instance = counter();
+    let mut debugger = Debugger::new();
+    debugger.execute(&Command::Break(Some(0x202)), &mut instance);
+    debugger.execute(&Command::Continue, &mut instance);
+
+    let stop = debugger.run_frame(&mut instance, 10).unwrap();
+    assert!(stop.starts_with("Breakpoint"));
+    assert!(debugger.paused());
+    assert_eq!(instance.pc(), 0x202);
+    assert_eq!(instance.v()[0], 1);
+
+    // continuing leaves the breakpoint before stopping on it again
+    debugger.execute(&Command::Continue, &mut instance);
+    assert!(debugger.run_frame(&mut instance, 10).is_some());
+    assert_eq!(instance.v()[0], 2);
+
+    debugger.execute(&Command::Delete(None), &mut instance);
+    debugger.execute(&Command::Continue, &mut instance);
+    assert_eq!(debugger.run_frame(&mut instance, 10), None);
+    assert_eq!(instance.v()[0], 7);
+}
+
+#[test]
+fn execution_error_pauses() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.load_rom_bytes(&[0x00, 0xEE]).unwrap();
+    let mut debugger = Debugger::new();
+    debugger.execute(&Command::Continue, &mut instance);
+    let stop = debugger.run_frame(&mut instance, 10).unwrap();
+    assert!(stop.starts_with("Execution error"));
+    assert!(debugger.paused());
+}
+
+#[test]
+fn inspect_machine() {
+    let mut instance = counter();
+    let mut debugger = Debugger::new();
+    debugger.execute(&Command::Set(Register::V(0xF), 0xAB), &mut instance);
+    debugger.execute(&Command::Set(Register::DelayTimer, 5), &mut instance);
+    assert_eq!(instance.v()[0xF], 0xAB);
+    assert_eq!(
+        debugger.execute(&Command::Timers, &mut instance),
+        "DT 5  ST 0"
+    );
+    assert_eq!(
+        debugger.execute(
+            &Command::Mem {
+                addr: 0x200,
+                len: 4
+            },
+            &mut instance
+        ),
+        "0x0200  70 01 12 00"
+    );
+    // up to the end of memory
+    let output = debugger.execute(
+        &Command::Mem {
+            addr: 0xFFF,
+            len: usize::MAX,
+        },
+        &mut instance,
+    );
+    assert_eq!(output, "0x0FFF  00");
+    assert_eq!(
+        debugger.execute(
+            &Command::Disasm {
+                addr: None,
+                count: 2
+            },
+            &mut instance
+        ),
+        "0x0200  ADD V0, 0x01\n0x0202  JP 0x200"
+    );
+    assert_eq!(
+        debugger.execute(&Command::Stack, &mut instance),
+        "The stack is empty"
+    );
+}
+
+#[test]
+fn parse_watches() {
+    assert_eq!(
+        Command::parse("watch 0x300 16 w"),
+        Ok(Command::Watch(Some(Watch::Memory {
+            start: 0x300,
+            end: 0x30F,
+            read: false,
+            write: true
+        })))
+    );
+    assert_eq!(
+        Command::parse("watch 0x300"),
+        Ok(Command::Watch(Some(Watch::Memory {
+            start: 0x300,
+            end: 0x300,
+            read: true,
+            write: true
+        })))
+    );
+    assert_eq!(
+        Command::parse("watch vf 1"),
+        Ok(Command::Watch(Some(Watch::Register(Register::V(0xF), 1))))
+    );
+    assert_eq!(Command::parse("watch"), Ok(Command::Watch(None)));
+    assert!(Command::parse("watch 0xFFFF 2").is_err());
+    assert!(Command::parse("watch v0 0x100").is_err());
+}
+
+#[test]
+fn watchpoint_stops_on_write() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance
+        .load_rom_bytes(&[
+            0xA3, 0x00, // 0x200: I = 0x300
+            0xD0, 0x01, // 0x202: draw a 1 byte sprite from I (a read)
+            0x60, 0x2A, // 0x204: v0 = 42
+            0xF2, 0x55, // 0x206: store v0 - v2 at I
+            0x12, 0x00, // 0x208: loop
+        ])
+        .unwrap();
+    let mut debugger = Debugger::new();
+    debugger.execute(&Command::parse("watch 0x302 w").unwrap(), &mut instance);
+    debugger.execute(&Command::Continue, &mut instance);
+
+    let stop = debugger.run_frame(&mut instance, 10).unwrap();
+    assert!(stop.starts_with("Watchpoint: wrote 0x00 at 0x0302 by the instruction at 0x0206"));
+    assert_eq!(instance.pc(), 0x208);
+
+    // reads only stop read watchpoints
+    debugger.execute(&Command::Unwatch(None), &mut instance);
+    debugger.execute(&Command::parse("watch 0x300 3 r").unwrap(), &mut instance);
+    debugger.execute(&Command::Continue, &mut instance);
+    let stop = debugger.run_frame(&mut instance, 10).unwrap();
+    assert!(stop.contains("read 0x2A at 0x0300 by the instruction at 0x0202"));
+    assert_eq!(instance.pc(), 0x204);
+
+    debugger.execute(&Command::Unwatch(Some(1)), &mut instance);
+    debugger.execute(&Command::Continue, &mut instance);
+    assert_eq!(debugger.run_frame(&mut instance, 10), None);
+}
+
+#[test]
+fn register_watch_stops_when_value_taken() {
+    let mut instance = counter();
+    let mut debugger = Debugger::new();
+    debugger.execute(&Command::parse("watch v0 5").unwrap(), &mut instance);
+    debugger.execute(&Command::Continue, &mut instance);
+
+    let stop = debugger.run_frame(&mut instance, 100).unwrap();
+    assert!(stop.starts_with("Watchpoint: V0 = 0x5 by the instruction at 0x0200"));
+    assert_eq!(instance.v()[0], 5);
+
+    // it only stops again once the register took another value
+    debugger.execute(&Command::Continue, &mut instance);
+    assert_eq!(debugger.run_frame(&mut instance, 100), None);
+}
@@ -0,0 +1,338 @@
+// Disassembler: instructions back to text, in the mnemonics of Cowgod's technical reference or
+// in Octo's language.
+//
+// A whole ROM is disassembled by following the control flow from 0x200: only what can be
+// reached is shown as code, the rest (sprites, tables, unused bytes) as data. Jumps to
+// computed addresses (bnnn) can't be followed, the code they reach is shown as data.
+use crate::chip8::Mode;
+use crate::instruction::{decode, Instruction};
+use std::collections::{BTreeMap, BTreeSet};
+
+// where ROMs are loaded
+const START: usize = 0x200;
+// data bytes per line
+const BYTES_PER_LINE: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Syntax {
+    Cowgod,
+    Octo,
+}
+
+// The instruction at addr and its size in bytes, with the addresses as numbers. Words that aren't
+// instructions of the mode are shown as data
+pub fn disassemble(memory: &[u8], addr: usize, mode: Mode) -> (String, usize) {
+    let instruction = decode(word(memory, addr));
+    if !instruction.supported_by(mode) {
+        return (format!("DW {:#06X}", word(memory, addr)), 2);
+    }
+    let long = word(memory, addr + 2);
+    let text = format(instruction, long, Syntax::Cowgod, &|addr| {
+        format!("{:#05X}", addr)
+    });
+    (text, instruction.size())
+}
+
+// An instruction as text. long is the operand of f000, address gives the text of the addresses
+pub fn format(
+    instruction: Instruction,
+    long: u16,
+    syntax: Syntax,
+    address: &dyn Fn(u16) -> String,
+) -> String {
+    match syntax {
+        Syntax::Cowgod => cowgod(instruction, long, address),
+        Syntax::Octo => octo(instruction, long, address),
+    }
+}
+
+fn cowgod(instruction: Instruction, long: u16, address: &dyn Fn(u16) -> String) -> String {
+    match instruction {
+        Instruction::Cls => "CLS".to_string(),
+        Instruction::Ret => "RET".to_string(),
+        Instruction::ScrollDown(n) => format!("SCD {}", n),
+        Instruction::ScrollUp(n) => format!("SCU {}", n),
+        Instruction::ScrollRight => "SCR".to_string(),
+        Instruction::ScrollLeft => "SCL".to_string(),
+        Instruction::Exit => "EXIT".to_string(),
+        Instruction::Lores => "LOW".to_string(),
+        Instruction::Hires => "HIGH".to_string(),
+        Instruction::Jump(nnn) => format!("JP {}", address(nnn)),
+        Instruction::Call(nnn) => format!("CALL {}", address(nnn)),
+        Instruction::SkipEqImm(x, kk) => format!("SE V{:X}, {:#04X}", x, kk),
+        Instruction::SkipNeImm(x, kk) => format!("SNE V{:X}, {:#04X}", x, kk),
+        Instruction::SkipEq(x, y) => format!("SE V{:X}, V{:X}", x, y),
+        Instruction::StoreRange(x, y) => format!("SAVE V{:X} - V{:X}", x, y),
+        Instruction::LoadRange(x, y) => format!("LOAD V{:X} - V{:X}", x, y),
+        Instruction::LoadImm(x, kk) => format!("LD V{:X}, {:#04X}", x, kk),
+        Instruction::AddImm(x, kk) => format!("ADD V{:X}, {:#04X}", x, kk),
+        Instruction::Move(x, y) => format!("LD V{:X}, V{:X}", x, y),
+        Instruction::Or(x, y) => format!("OR V{:X}, V{:X}", x, y),
+        Instruction::And(x, y) => format!("AND V{:X}, V{:X}", x, y),
+        Instruction::Xor(x, y) => format!("XOR V{:X}, V{:X}", x, y),
+        Instruction::Add(x, y) => format!("ADD V{:X}, V{:X}", x, y),
+        Instruction::Sub(x, y) => format!("SUB V{:X}, V{:X}", x, y),
+        Instruction::ShiftRight(x, y) => format!("SHR V{:X}, V{:X}", x, y),
+        Instruction::SubN(x, y) => format!("SUBN V{:X}, V{:X}", x, y),
+        Instruction::ShiftLeft(x, y) => format!("SHL V{:X}, V{:X}", x, y),
+        Instruction::SkipNe(x, y) => format!("SNE V{:X}, V{:X}", x, y),
+        Instruction::LoadI(nnn) => format!("LD I, {}", address(nnn)),
+        // nnn includes x, the register added with the jump quirk
+        Instruction::JumpOffset(_, nnn) => format!("JP V0, {}", address(nnn)),
+        Instruction::Random(x, kk) => format!("RND V{:X}, {:#04X}", x, kk),
+        Instruction::Draw(x, y, n) => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        Instruction::SkipKey(x) => format!("SKP V{:X}", x),
+        Instruction::SkipNotKey(x) => format!("SKNP V{:X}", x),
+        Instruction::LoadILong => format!("LD I, long {}", address(long)),
+        Instruction::Plane(n) => format!("PLANE {}", n),
+        Instruction::Audio => "AUDIO".to_string(),
+        Instruction::GetDelay(x) => format!("LD V{:X}, DT", x),
+        Instruction::WaitKey(x) => format!("LD V{:X}, K", x),
+        Instruction::SetDelay(x) => format!("LD DT, V{:X}", x),
+        Instruction::SetSound(x) => format!("LD ST, V{:X}", x),
+        Instruction::AddI(x) => format!("ADD I, V{:X}", x),
+        Instruction::Font(x) => format!("LD F, V{:X}", x),
+        Instruction::BigFont(x) => format!("LD HF, V{:X}", x),
+        Instruction::Bcd(x) => format!("LD B, V{:X}", x),
+        Instruction::Pitch(x) => format!("PITCH V{:X}", x),
+        Instruction::StoreRegisters(x) => format!("LD [I], V{:X}", x),
+        Instruction::LoadRegisters(x) => format!("LD V{:X}, [I]", x),
+        Instruction::StoreFlags(x) => format!("LD R, V{:X}", x),
+        Instruction::LoadFlags(x) => format!("LD V{:X}, R", x),
+        Instruction::Unknown(opcode) => format!("DW {:#06X}", opcode),
+    }
+}
+
+fn octo(instruction: Instruction, long: u16, address: &dyn Fn(u16) -> String) -> String {
+    match instruction {
+        Instruction::Cls => "clear".to_string(),
+        Instruction::Ret => "return".to_string(),
+        Instruction::ScrollDown(n) => format!("scroll-down {}", n),
+        Instruction::ScrollUp(n) => format!("scroll-up {}", n),
+        Instruction::ScrollRight => "scroll-right".to_string(),
+        Instruction::ScrollLeft => "scroll-left".to_string(),
+        Instruction::Exit => "exit".to_string(),
+        Instruction::Lores => "lores".to_string(),
+        Instruction::Hires => "hires".to_string(),
+        Instruction::Jump(nnn) => format!("jump {}", address(nnn)),
+        Instruction::Call(nnn) => format!(":call {}", address(nnn)),
+        // octo names the condition under which the next instruction runs
+        Instruction::SkipEqImm(x, kk) => format!("if v{:x} != {:#04X} then", x, kk),
+        Instruction::SkipNeImm(x, kk) => format!("if v{:x} == {:#04X} then", x, kk),
+        Instruction::SkipEq(x, y) => format!("if v{:x} != v{:x} then", x, y),
+        Instruction::StoreRange(x, y) => format!("save v{:x} - v{:x}", x, y),
+        Instruction::LoadRange(x, y) => format!("load v{:x} - v{:x}", x, y),
+        Instruction::LoadImm(x, kk) => format!("v{:x} := {:#04X}", x, kk),
+        Instruction::AddImm(x, kk) => format!("v{:x} += {:#04X}", x, kk),
+        Instruction::Move(x, y) => format!("v{:x} := v{:x}", x, y),
+        Instruction::Or(x, y) => format!("v{:x} |= v{:x}", x, y),
+        Instruction::And(x, y) => format!("v{:x} &= v{:x}", x, y),
+        Instruction::Xor(x, y) => format!("v{:x} ^= v{:x}", x, y),
+        Instruction::Add(x, y) => format!("v{:x} += v{:x}", x, y),
+        Instruction::Sub(x, y) => format!("v{:x} -= v{:x}", x, y),
+        Instruction::ShiftRight(x, y) => format!("v{:x} >>= v{:x}", x, y),
+        Instruction::SubN(x, y) => format!("v{:x} =- v{:x}", x, y),
+        Instruction::ShiftLeft(x, y) => format!("v{:x} <<= v{:x}", x, y),
+        Instruction::SkipNe(x, y) => format!("if v{:x} == v{:x} then", x, y),
+        Instruction::LoadI(nnn) => format!("i := {}", address(nnn)),
+        Instruction::JumpOffset(_, nnn) => format!("jump0 {}", address(nnn)),
+        Instruction::Random(x, kk) => format!("v{:x} := random {:#04X}", x, kk),
+        Instruction::Draw(x, y, n) => format!("sprite v{:x} v{:x} {}", x, y, n),
+        Instruction::SkipKey(x) => format!("if v{:x} -key then", x),
+        Instruction::SkipNotKey(x) => format!("if v{:x} key then", x),
+        Instruction::LoadILong => format!("i := long {}", address(long)),
+        Instruction::Plane(n) => format!("plane {}", n),
+        Instruction::Audio => "audio".to_string(),
+        Instruction::GetDelay(x) => format!("v{:x} := delay", x),
+        Instruction::WaitKey(x) => format!("v{:x} := key", x),
+        Instruction::SetDelay(x) => format!("delay := v{:x}", x),
+        Instruction::SetSound(x) => format!("buzzer := v{:x}", x),
+        Instruction::AddI(x) => format!("i += v{:x}", x),
+        Instruction::Font(x) => format!("i := hex v{:x}", x),
+        Instruction::BigFont(x) => format!("i := bighex v{:x}", x),
+        Instruction::Bcd(x) => format!("bcd v{:x}", x),
+        Instruction::Pitch(x) => format!("pitch := v{:x}", x),
+        Instruction::StoreRegisters(x) => format!("save v{:x}", x),
+        Instruction::LoadRegisters(x) => format!("load v{:x}", x),
+        Instruction::StoreFlags(x) => format!("saveflags v{:x}", x),
+        Instruction::LoadFlags(x) => format!("loadflags v{:x}", x),
+        Instruction::Unknown(opcode) => format!("{:#04X} {:#04X}", opcode >> 8, opcode & 0xFF),
+    }
+}
+
+// What a label points to, the most important kind names it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Target {
+    // loaded in I
+    Data,
+    // jumped to
+    Label,
+    // called
+    Subroutine,
+}
+
+// The code reachable from 0x200 and the addresses it refers to
+struct Analysis {
+    // the start of each instruction
+    code: BTreeMap<usize, Instruction>,
+    targets: BTreeMap<usize, Target>,
+}
+
+fn analyze(memory: &[u8], end: usize, mode: Mode) -> Analysis {
+    let mut code = BTreeMap::new();
+    let mut targets = BTreeMap::new();
+    let mut target = |addr: u16, kind: Target| {
+        let entry = targets.entry(addr as usize).or_insert(kind);
+        *entry = (*entry).max(kind);
+    };
+
+    let mut pending = vec![START];
+    while let Some(addr) = pending.pop() {
+        if addr < START || code.contains_key(&addr) {
+            continue;
+        }
+        let instruction = decode(word(memory, addr));
+        if addr + instruction.size() > end || !instruction.supported_by(mode) {
+            continue;
+        }
+        code.insert(addr, instruction);
+        let next = addr + instruction.size();
+        match instruction {
+            Instruction::Jump(nnn) => {
+                target(nnn, Target::Label);
+                pending.push(nnn as usize);
+            }
+            Instruction::Call(nnn) => {
+                target(nnn, Target::Subroutine);
+                pending.push(nnn as usize);
+                pending.push(next);
+            }
+            Instruction::Ret | Instruction::Exit => {}
+            Instruction::JumpOffset(_, nnn) => target(nnn, Target::Label),
+            Instruction::LoadI(nnn) => {
+                target(nnn, Target::Data);
+                pending.push(next);
+            }
+            Instruction::LoadILong => {
+                target(word(memory, addr + 2), Target::Data);
+                pending.push(next);
+            }
+            _ if instruction.is_skip() => {
+                pending.push(next);
+                // skipping f000 nnnn skips 4 bytes
+                let skipped = decode(word(memory, next));
+                let skipped = if skipped.supported_by(mode) {
+                    skipped.size()
+                } else {
+                    2
+                };
+                pending.push(next + skipped);
+            }
+            _ => pending.push(next),
+        }
+    }
+    Analysis { code, targets }
+}
+
+// A line of the listing
+enum Item {
+    Code(usize, Instruction),
+    Data(usize, usize),
+}
+
+// Disassemble a ROM, as it is loaded at 0x200
+pub fn disassemble_rom(rom: &[u8], mode: Mode, syntax: Syntax) -> String {
+    let mut memory = vec![0; START];
+    memory.extend_from_slice(rom);
+    let end = memory.len();
+    let analysis = analyze(&memory, end, mode);
+
+    // cut the ROM in instructions and data, data stops at the instructions and the labels
+    let mut items = Vec::new();
+    let mut addr = START;
+    while addr < end {
+        if let Some(&instruction) = analysis.code.get(&addr) {
+            items.push(Item::Code(addr, instruction));
+            addr += instruction.size();
+            continue;
+        }
+        let mut len = 1;
+        while addr + len < end
+            && len < BYTES_PER_LINE
+            && !analysis.code.contains_key(&(addr + len))
+            && !analysis.targets.contains_key(&(addr + len))
+        {
+            len += 1;
+        }
+        items.push(Item::Data(addr, len));
+        addr += len;
+    }
+
+    // only the targets at the start of a line can be labelled
+    let starts: BTreeSet<usize> = items
+        .iter()
+        .map(|item| match *item {
+            Item::Code(addr, _) | Item::Data(addr, _) => addr,
+        })
+        .collect();
+    let labels: BTreeMap<usize, String> = analysis
+        .targets
+        .iter()
+        .filter(|(addr, _)| starts.contains(addr))
+        .map(|(&addr, &kind)| (addr, label(addr, kind, syntax)))
+        .collect();
+    let address = |addr: u16| match labels.get(&(addr as usize)) {
+        Some(label) => label.clone(),
+        None => format!("{:#05X}", addr),
+    };
+
+    let mut out = String::new();
+    if syntax == Syntax::Octo && !labels.contains_key(&START) {
+        // octo programs start at main
+        out.push_str(": main\n");
+    }
+    for item in items {
+        let (addr, line) = match item {
+            Item::Code(addr, instruction) => {
+                let long = word(&memory, addr + 2);
+                (addr, format(instruction, long, syntax, &address))
+            }
+            Item::Data(addr, len) => (addr, data(&memory[addr..addr + len], syntax)),
+        };
+        if let Some(label) = labels.get(&addr) {
+            match syntax {
+                Syntax::Cowgod => out.push_str(&format!("{}:\n", label)),
+                Syntax::Octo => out.push_str(&format!(": {}\n", label)),
+            }
+        }
+        out.push_str(&format!("    {}\n", line));
+    }
+    out
+}
+
+fn label(addr: usize, kind: Target, syntax: Syntax) -> String {
+    match kind {
+        _ if addr == START && syntax == Syntax::Octo => "main".to_string(),
+        Target::Data => format!("data_{:03X}", addr),
+        Target::Label => format!("label_{:03X}", addr),
+        Target::Subroutine => format!("sub_{:03X}", addr),
+    }
+}
+
+fn data(bytes: &[u8], syntax: Syntax) -> String {
+    let bytes: Vec<String> = bytes.iter().map(|byte| format!("{:#04X}", byte)).collect();
+    match syntax {
+        Syntax::Cowgod => format!("DB {}", bytes.join(", ")),
+        Syntax::Octo => bytes.join(" "),
+    }
+}
+
+// the big endian word at addr, 0 past the end of memory
+fn word(memory: &[u8], addr: usize) -> u16 {
+    let byte = |addr: usize| *memory.get(addr).unwrap_or(&0) as u16;
+    byte(addr) << 8 | byte(addr + 1)
+}
+
+#[cfg(test)]
+#[path = "./disasm_tests.rs"]
+mod disasm_tests;
@@ -0,0 +1,108 @@
+use super::*;
+
+#[test]
+fn disassemble_chip8() {
+    let memory = [0x00, 0xE0, 0x6A, 0x12, 0xD0, 0x15, 0xF3, 0x55, 0x8A, 0xB4];
+    let listing: Vec<String> = (0..memory.len())
+        .step_by(2)
+        .map(|addr| disassemble(&memory, addr, Mode::Chip8).0)
+        .collect();
+    assert_eq!(
+        listing,
+        [
+            "CLS",
+            "LD VA, 0x12",
+            "DRW V0, V1, 5",
+            "LD [I], V3",
+            "ADD VA, VB"
+        ]
+    );
+}
+
+#[test]
+fn disassemble_depends_on_mode() {
+    let memory = [0x00, 0xFF];
+    assert_eq!(disassemble(&memory, 0, Mode::Chip8).0, "DW 0x00FF");
+    assert_eq!(disassemble(&memory, 0, Mode::SuperChip).0, "HIGH");
+}
+
+#[test]
+fn disassemble_long_load() {
+    let memory = [0xF0, 0x00, 0x12, 0x34];
+    assert_eq!(
+        disassemble(&memory, 0, Mode::XoChip),
+        ("LD I, long 0x1234".to_string(), 4)
+    );
+    assert_eq!(disassemble(&memory, 0, Mode::SuperChip).1, 2);
+}
+
+// a loop drawing a sprite, calling a subroutine, and a sprite after the code
+const ROM: [u8; 14] = [
+    0xA2, 0x0C, // 0x200: I = the sprite
+    0xD0, 0x11, // 0x202: draw it
+    0x22, 0x08, // 0x204: call 0x208
+    0x12, 0x00, // 0x206: loop
+    0x6F, 0x01, // 0x208: vf = 1
+    0x00, 0xEE, // 0x20A: return
+    0x80, 0x42, // 0x20C: the sprite (never executed)
+];
+
+#[test]
+fn disassemble_rom_cowgod() {
+    assert_eq!(
+        disassemble_rom(&ROM, Mode::Chip8, Syntax::Cowgod),
+        "label_200:
+    LD I, data_20C
+    DRW V0, V1, 1
+    CALL sub_208
+    JP label_200
+sub_208:
+    LD VF, 0x01
+    RET
+data_20C:
+    DB 0x80, 0x42
+"
+    );
+}
+
+#[test]
+fn disassemble_rom_octo() {
+    assert_eq!(
+        disassemble_rom(&ROM, Mode::Chip8, Syntax::Octo),
+        ": main
+    i := data_20C
+    sprite v0 v1 1
+    :call sub_208
+    jump main
+: sub_208
+    vf := 0x01
+    return
+: data_20C
+    0x80 0x42
+"
+    );
+}
+
+#[test]
+fn disassemble_rom_follows_skips_over_long_load() {
+    let rom = [
+        0x30, 0x00, // 0x200: skip if v0 == 0
+        0xF0, 0x00, 0x02, 0x0A, // 0x202: I = 0x20A
+        0x12, 0x08, // 0x206: jump 0x208
+        0x12, 0x08, // 0x208: loop
+        0xFF, // 0x20A: data
+    ];
+    assert_eq!(
+        disassemble_rom(&rom, Mode::XoChip, Syntax::Cowgod),
+        "    SE V0, 0x00
+    LD I, long data_20A
+    JP label_208
+label_208:
+    JP label_208
+data_20A:
+    DB 0xFF
+"
+    );
+    // on a plain CHIP-8 f000 isn't an instruction
+    assert!(disassemble_rom(&rom, Mode::Chip8, Syntax::Cowgod).contains("DB 0xF0, 0x00"));
+}
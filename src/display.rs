@@ -1,53 +1,118 @@
-use sdl2;
-use sdl2::pixels::Color;
+use sdl2::pixels::{Color, PixelFormatEnum};
 use sdl2::rect::Rect;
-use crate::chip8::{WIDTH, HEIGHT};
+use sdl2::render::Texture;
+use sdl2::video::FullscreenType;
+use chip8_emulator::chip8::{HEIGHT, WIDTH};
+use chip8_emulator::palette::Rgb;
+use chip8_emulator::persistence::Persistence;
+use chip8_emulator::viewport::{self, Scaling};
+use chip8_emulator::{Framebuffer, Palette};
 
-const SCREEN_WIDTH: usize = 1280;
-const SCREEN_HEIGH: usize = 640;
-const SCALE: usize = 20;
+// the size of the lores pixels when the window opens: 1280x640
+pub const DEFAULT_SCALE: u32 = 20;
 
 pub struct Display {
     // the canvas I will be drawing to
     pub canvas: sdl2::render::Canvas<sdl2::video::Window>,
     pub context: sdl2::Sdl,
+    // the colors of the pixel values
+    pub palette: Palette,
+    pub scaling: Scaling,
+    // the color around the screen, the background color of the palette when not set
+    pub border: Option<Rgb>,
+    // the flicker reduction, the colors of the screen go through it
+    persistence: Persistence,
+    // the screen, one texel per pixel, scaled to the window when copied. Created on the first
+    // draw and again when the resolution changes
+    texture: Option<Texture>,
 }
 
 impl Display {
-    pub fn initialize() -> Display {
+    pub fn initialize(
+        palette: Palette,
+        scale: u32,
+        scaling: Scaling,
+        border: Option<Rgb>,
+        persistence: Persistence,
+    ) -> Display {
         let sdl = sdl2::init().expect("Failed to initialize display!");
         let video_subsystem = sdl.video().expect("Failed to load video subsystem.");
+        let (width, height) = (WIDTH as u32, HEIGHT as u32);
         let window = video_subsystem
-            .window("Chip-8 Emulator", SCREEN_WIDTH as u32, SCREEN_HEIGH as u32)
+            .window("Chip-8 Emulator", width.saturating_mul(scale), height.saturating_mul(scale))
             .resizable()
             .build()
             .expect("Failed to build window");
+        // sharp pixels at any size
+        sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "nearest");
         Display {
             canvas: window
                 .into_canvas()
                 .build()
                 .expect("Failed to build canvas"),
-            context: sdl
+            context: sdl,
+            palette,
+            scaling,
+            border,
+            persistence,
+            texture: None,
         }
     }
-    pub fn draw(&mut self, display: &[[u8; WIDTH]; HEIGHT]) -> Result<(), String> {
-        self.canvas.set_draw_color(Color::RGB(0, 0, 0));
-        self.canvas.clear();
-        self.canvas.set_draw_color(Color::RGB(255, 255, 255));
-        for y in 0..HEIGHT {
-            for x in 0..WIDTH {
-                //println!("x:{} y:{} state: {}", y, x, display[y][x]);
-                if display[y][x] == 1 {
-                    self.canvas.fill_rect(Rect::new(
-                        x as i32 * SCALE as i32,
-                        y as i32 * SCALE as i32,
-                        20,
-                        20,
-                    ))?;
-                }
+
+    pub fn draw(&mut self, display: &Framebuffer) -> Result<(), String> {
+        let (width, height) = (display.width() as u32, display.height() as u32);
+        let resized = self.texture.as_ref().is_none_or(|texture| {
+            let query = texture.query();
+            (query.width, query.height) != (width, height)
+        });
+        if resized {
+            if let Some(texture) = self.texture.take() {
+                // the renderer would only free it when the window closes
+                unsafe { texture.destroy() };
             }
+            let texture = self
+                .canvas
+                .texture_creator()
+                .create_texture_streaming(PixelFormatEnum::RGB24, width, height)
+                .map_err(|e| e.to_string())?;
+            self.texture = Some(texture);
         }
+        let texture = self.texture.as_mut().expect("the texture was just created");
+
+        let image = self.persistence.render(display, &self.palette);
+        texture.with_lock(None, |texels, pitch| {
+            for (y, row) in image.chunks(width as usize).enumerate() {
+                let line = &mut texels[y * pitch..];
+                for (x, color) in row.iter().enumerate() {
+                    line[x * 3..x * 3 + 3].copy_from_slice(color);
+                }
+            }
+        })?;
+
+        let [r, g, b] = self.border.unwrap_or(self.palette.colors[0]);
+        self.canvas.set_draw_color(Color::RGB(r, g, b));
+        self.canvas.clear();
+        let window = self.canvas.output_size()?;
+        let area = viewport::viewport(window, (width, height), self.scaling);
+        let target = Rect::new(area.x as i32, area.y as i32, area.width, area.height);
+        self.canvas.copy(texture, None, target)?;
         self.canvas.present();
         Ok(())
     }
+
+    // whether the screen shown is still fading, and must be drawn on the next frame even if the
+    // machine didn't draw
+    pub fn fading(&self) -> bool {
+        self.persistence.fading()
+    }
+
+    // switch between the window and fullscreen at the resolution of the desktop
+    pub fn toggle_fullscreen(&mut self) -> Result<(), String> {
+        let window = self.canvas.window_mut();
+        let state = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
+        window.set_fullscreen(state)
+    }
 }
@@ -0,0 +1,55 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+// The faults the emulated machine can run into
+#[derive(Debug)]
+pub enum Chip8Error {
+    // the opcode at pc isn't an instruction of the active mode
+    UnknownOpcode { pc: u16, opcode: u16 },
+    // a call with all the 16 stack frames in use
+    StackOverflow,
+    // a return with an empty stack
+    StackUnderflow,
+    // a memory access outside the address space
+    MemoryOutOfBounds { addr: usize },
+    // the ROM doesn't fit in memory after 0x200
+    RomTooLarge { size: usize },
+    // a save state that is corrupted or from an incompatible version
+    InvalidState(String),
+    // a file couldn't be read or written: the ROM, a trace
+    Io(io::Error),
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Chip8Error::UnknownOpcode { pc, opcode } => {
+                write!(f, "Unknown instruction {:#06X} at {:#06X}", opcode, pc)
+            }
+            Chip8Error::StackOverflow => write!(f, "Stack is full"),
+            Chip8Error::StackUnderflow => write!(f, "Tried to return with an empty stack"),
+            Chip8Error::MemoryOutOfBounds { addr } => {
+                write!(f, "Memory access out of bounds at {:#06X}", addr)
+            }
+            Chip8Error::RomTooLarge { size } => write!(f, "ROM is too large ({} bytes)", size),
+            Chip8Error::InvalidState(reason) => write!(f, "Invalid save state: {}", reason),
+            Chip8Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for Chip8Error {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Chip8Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Chip8Error {
+    fn from(e: io::Error) -> Chip8Error {
+        Chip8Error::Io(e)
+    }
+}
@@ -0,0 +1,111 @@
+// The screen of the emulated machine: a width x height grid of pixels, stored row by row.
+// The size changes at runtime when a SUPER-CHIP program switches between lores and hires.
+// Each pixel is a bitmask of the XO-CHIP planes it is lit in (plain CHIP-8 only uses plane 1),
+// so a pixel value is one of 4 colors.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    // a framebuffer with existing content, pixels holds the width x height values row by row
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<u8>) -> Framebuffer {
+        assert_eq!(pixels.len(), width * height);
+        Framebuffer {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: u8) {
+        self.pixels[y * self.width + x] = value;
+    }
+
+    pub fn row(&self, y: usize) -> &[u8] {
+        &self.pixels[y * self.width..(y + 1) * self.width]
+    }
+
+    // all the pixels, row by row
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn rows(&self) -> std::slice::Chunks<'_, u8> {
+        self.pixels.chunks(self.width)
+    }
+
+    // turn off the selected planes in every pixel
+    pub fn clear(&mut self, planes: u8) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel &= !planes;
+        }
+    }
+
+    // change the resolution, the content is lost
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.pixels = vec![0; width * height];
+    }
+
+    // move the selected planes n rows down, the top rows become blank
+    pub fn scroll_down(&mut self, n: usize, planes: u8) {
+        self.scroll(0, n as isize, planes);
+    }
+
+    // move the selected planes n rows up, the bottom rows become blank
+    pub fn scroll_up(&mut self, n: usize, planes: u8) {
+        self.scroll(0, -(n as isize), planes);
+    }
+
+    // move the selected planes n columns right, the leftmost columns become blank
+    pub fn scroll_right(&mut self, n: usize, planes: u8) {
+        self.scroll(n as isize, 0, planes);
+    }
+
+    // move the selected planes n columns left, the rightmost columns become blank
+    pub fn scroll_left(&mut self, n: usize, planes: u8) {
+        self.scroll(-(n as isize), 0, planes);
+    }
+
+    // move the selected planes by (dx, dy), the other planes stay where they are
+    fn scroll(&mut self, dx: isize, dy: isize, planes: u8) {
+        let (width, height) = (self.width as isize, self.height as isize);
+        let old = self.pixels.clone();
+        for y in 0..height {
+            for x in 0..width {
+                let (src_x, src_y) = (x - dx, y - dy);
+                let moved = if src_x >= 0 && src_x < width && src_y >= 0 && src_y < height {
+                    old[(src_y * width + src_x) as usize] & planes
+                } else {
+                    0
+                };
+                let pixel = &mut self.pixels[(y * width + x) as usize];
+                *pixel = (*pixel & !planes) | moved;
+            }
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use chip8_emulator::config::Hotkey;
+use chip8_emulator::{Chip8, Palette};
+
+// What the main loop runs the machine through: the window (SDL) or the terminal. Called once per
+// frame: handle_input, then draw when the screen changed or needs_draw, then beep or stop_beep
+pub trait Frontend {
+    // Handles the input since the last frame: the keypad keys are pressed and released on the
+    // machine, when given. Returns the hotkeys that were pressed, and Rewind while its key is held
+    fn handle_input(&mut self, keypad: Option<&mut Chip8>) -> Vec<Hotkey>;
+
+    fn draw(&mut self, chip8: &Chip8) -> Result<(), String>;
+
+    // whether the screen must be drawn even if the machine didn't draw: resized, fading out...
+    fn needs_draw(&mut self) -> bool;
+
+    // the sound timer is running
+    fn beep(&mut self, chip8: &Chip8);
+
+    fn stop_beep(&mut self);
+
+    // the colors of the pixel values from the next draw
+    fn set_palette(&mut self, palette: Palette);
+
+    fn toggle_fullscreen(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    // tell the user about a hotkey, or an error
+    fn message(&mut self, text: &str) {
+        println!("{}", text);
+    }
+
+    fn error(&mut self, text: &str) {
+        eprintln!("{}", text);
+    }
+}
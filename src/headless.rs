@@ -0,0 +1,244 @@
+// Running ROMs without a window, sound or keyboard: for tests and CI.
+//
+// The machine runs for a number of frames, or until it reaches an address or an opcode, with
+// the keys pressed and released on the frames given by a script. The result can then be dumped:
+// the screen as ASCII art or PNG, the registers as JSON.
+//
+// Key scripts list one event per line (or separated by ;), # starts a comment:
+//     30 down 5       key 5 is pressed before frame 30 runs
+//     90 up 5         and released before frame 90
+use crate::chip8::{Chip8, Mode, NUM_KEYS};
+use crate::framebuffer::Framebuffer;
+use crate::palette::Palette;
+use crate::png;
+
+// the characters of the pixel values in ASCII art
+const CHARACTERS: [char; 4] = ['.', '#', 'o', '@'];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyEvent {
+    // the frame before which the key changes
+    pub frame: usize,
+    pub key: usize,
+    pub pressed: bool,
+}
+
+// Parse a key script, errors give the line (1 based) of the event
+pub fn parse_keys(script: &str) -> Result<Vec<KeyEvent>, String> {
+    let mut events = Vec::new();
+    for (index, line) in script.lines().enumerate() {
+        let code = line.split('#').next().unwrap_or("");
+        for event in code.split(';').filter(|event| !event.trim().is_empty()) {
+            let event = parse_key(event).ok_or_else(|| {
+                format!("line {}: invalid key event: {}", index + 1, event.trim())
+            })?;
+            events.push(event);
+        }
+    }
+    // the events of a frame keep their order
+    events.sort_by_key(|event| event.frame);
+    Ok(events)
+}
+
+// <frame> down|up <key>
+fn parse_key(event: &str) -> Option<KeyEvent> {
+    let words: Vec<&str> = event.split_whitespace().collect();
+    match words.as_slice() {
+        [frame, action, key] => {
+            let pressed = match *action {
+                "down" => true,
+                "up" => false,
+                _ => return None,
+            };
+            let key = usize::from_str_radix(key, 16)
+                .ok()
+                .filter(|&key| key < NUM_KEYS)?;
+            Some(KeyEvent {
+                frame: frame.parse().ok()?,
+                key,
+                pressed,
+            })
+        }
+        _ => None,
+    }
+}
+
+// What ends a run, besides the frame limit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Until {
+    // pc reaches the address, the instruction there isn't executed
+    Pc(u16),
+    // the instruction at pc is the opcode, it isn't executed
+    Opcode(u16),
+}
+
+// Why a run ended
+#[derive(Clone, Debug, PartialEq)]
+pub enum Stop {
+    // all the frames ran
+    Frames,
+    // the Until condition was met
+    Reached,
+    // the program ran 00FD
+    Exited,
+    // the machine faulted, pc is left on the faulty instruction
+    Error(String),
+}
+
+impl Stop {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stop::Frames => "frames",
+            Stop::Reached => "reached",
+            Stop::Exited => "exited",
+            Stop::Error(_) => "error",
+        }
+    }
+}
+
+// The end of a run
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outcome {
+    // the frames run, the last one included even when it stopped midway
+    pub frames: usize,
+    pub stop: Stop,
+}
+
+// Run at most frames frames of ipf instructions. keys must be sorted by frame
+pub fn run(
+    chip8: &mut Chip8,
+    ipf: usize,
+    frames: usize,
+    until: Option<Until>,
+    keys: &[KeyEvent],
+) -> Outcome {
+    let mut keys = keys.iter().peekable();
+    for frame in 0..frames {
+        while let Some(event) = keys.next_if(|event| event.frame <= frame) {
+            chip8.set_key(event.key, event.pressed);
+        }
+        for _ in 0..ipf {
+            if chip8.exited() {
+                return Outcome {
+                    frames: frame + 1,
+                    stop: Stop::Exited,
+                };
+            }
+            if until.is_some_and(|until| reached(chip8, until)) {
+                return Outcome {
+                    frames: frame + 1,
+                    stop: Stop::Reached,
+                };
+            }
+            if let Err(e) = chip8.step() {
+                return Outcome {
+                    frames: frame + 1,
+                    stop: Stop::Error(e.to_string()),
+                };
+            }
+        }
+        chip8.handle_timers();
+    }
+    Outcome {
+        frames,
+        stop: Stop::Frames,
+    }
+}
+
+fn reached(chip8: &Chip8, until: Until) -> bool {
+    let pc = chip8.pc();
+    match until {
+        Until::Pc(addr) => pc == addr,
+        Until::Opcode(opcode) => {
+            let memory = chip8.memory();
+            let pc = pc as usize;
+            pc + 1 < memory.len() && u16::from_be_bytes([memory[pc], memory[pc + 1]]) == opcode
+        }
+    }
+}
+
+// The screen as text, a line per row
+pub fn ascii(framebuffer: &Framebuffer) -> String {
+    let mut text = String::with_capacity((framebuffer.width() + 1) * framebuffer.height());
+    for row in framebuffer.rows() {
+        text.extend(row.iter().map(|&pixel| CHARACTERS[pixel as usize & 3]));
+        text.push('\n');
+    }
+    text
+}
+
+// The screen as a PNG image in the colors of the palette, every pixel a scale x scale square
+pub fn png(framebuffer: &Framebuffer, scale: usize, palette: &Palette) -> Vec<u8> {
+    let width = framebuffer.width() * scale;
+    let height = framebuffer.height() * scale;
+    let mut rgb = Vec::with_capacity(width * height * 3);
+    for row in framebuffer.rows() {
+        let start = rgb.len();
+        for &pixel in row {
+            for _ in 0..scale {
+                rgb.extend_from_slice(&palette.color(pixel));
+            }
+        }
+        // the other rows of the squares
+        for _ in 1..scale {
+            rgb.extend_from_within(start..start + width * 3);
+        }
+    }
+    png::encode(width, height, &rgb)
+}
+
+// The registers, the stack and the timers as a JSON object
+pub fn registers_json(chip8: &Chip8, outcome: &Outcome) -> String {
+    let list = |values: &mut dyn Iterator<Item = String>| {
+        format!("[{}]", values.collect::<Vec<String>>().join(", "))
+    };
+    let mode = match chip8.mode() {
+        Mode::Chip8 => "chip8",
+        Mode::SuperChip => "schip",
+        Mode::XoChip => "xochip",
+    };
+    let stack = &chip8.stack()[..chip8.sp() as usize];
+    let mut fields = vec![
+        ("mode", string(mode)),
+        ("frames", outcome.frames.to_string()),
+        ("stop", string(outcome.stop.name())),
+        ("pc", chip8.pc().to_string()),
+        ("i", chip8.i().to_string()),
+        ("v", list(&mut chip8.v().iter().map(|v| v.to_string()))),
+        ("sp", chip8.sp().to_string()),
+        (
+            "stack",
+            list(&mut stack.iter().map(|addr| addr.to_string())),
+        ),
+        ("delay_timer", chip8.delay_timer().to_string()),
+        ("sound_timer", chip8.sound_timer().to_string()),
+    ];
+    if let Stop::Error(error) = &outcome.stop {
+        fields.push(("error", string(error)));
+    }
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(name, value)| format!("  \"{}\": {}", name, value))
+        .collect();
+    format!("{{\n{}\n}}\n", fields.join(",\n"))
+}
+
+// a JSON string
+fn string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+#[path = "./headless_tests.rs"]
+mod headless_tests;
@@ -0,0 +1,100 @@
+use super::*;
+use crate::quirks::Quirks;
+
+// draws digit 0 at (0, 0), then counts in v1 while key 2 is held
+const ROM: [u8; 14] = [
+    0x60, 0x00, // 0x200: v0 = 0
+    0xF0, 0x29, // 0x202: I = font digit 0
+    0xD0, 0x05, // 0x204: draw it at (0, 0)
+    0x62, 0x02, // 0x206: v2 = 2
+    0xE2, 0xA1, // 0x208: skip the next instruction if key 2 isn't pressed
+    0x71, 0x01, // 0x20A: v1 += 1
+    0x12, 0x08, // 0x20C: loop
+];
+
+fn machine() -> Chip8 {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.load_rom_bytes(&ROM).unwrap();
+    instance
+}
+
+#[test]
+fn parse_key_script() {
+    let keys = parse_keys("# hold 5\n90 up 5; 30 down 5\n\n10 down a").unwrap();
+    let frames: Vec<(usize, usize, bool)> = keys
+        .iter()
+        .map(|event| (event.frame, event.key, event.pressed))
+        .collect();
+    assert_eq!(frames, [(10, 0xA, true), (30, 5, true), (90, 5, false)]);
+    assert_eq!(
+        parse_keys("1 down 5\n2 hold 5"),
+        Err("line 2: invalid key event: 2 hold 5".to_string())
+    );
+    assert!(parse_keys("1 down 10").is_err());
+}
+
+#[test]
+fn run_for_frames_with_keys() {
+    let mut instance = machine();
+    let keys = parse_keys("2 down 2; 4 up 2").unwrap();
+    let outcome = run(&mut instance, 10, 6, None, &keys);
+    assert_eq!(
+        outcome,
+        Outcome {
+            frames: 6,
+            stop: Stop::Frames
+        }
+    );
+    // the loop is 3 instructions long, 20 instructions run with the key held
+    assert!(instance.v()[1] >= 6);
+    assert!(!instance.keys()[2]);
+}
+
+#[test]
+fn run_until_pc_or_opcode() {
+    let mut instance = machine();
+    let outcome = run(&mut instance, 10, 100, Some(Until::Pc(0x208)), &[]);
+    assert_eq!(outcome.stop, Stop::Reached);
+    assert_eq!(outcome.frames, 1);
+    assert_eq!(instance.pc(), 0x208);
+
+    let mut instance = machine();
+    let outcome = run(&mut instance, 1, 100, Some(Until::Opcode(0xD005)), &[]);
+    assert_eq!(outcome.frames, 3);
+    assert_eq!(instance.pc(), 0x204);
+}
+
+#[test]
+fn dump_screen_and_registers() {
+    let mut instance = machine();
+    let outcome = run(&mut instance, 10, 1, None, &[]);
+    let text = ascii(instance.framebuffer());
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 32);
+    assert_eq!(&lines[0][..6], "####..");
+    assert_eq!(&lines[1][..6], "#..#..");
+
+    let image = png(instance.framebuffer(), 2, &Palette::default());
+    // the IHDR width and height
+    assert_eq!(image[16..24], [0, 0, 0, 128, 0, 0, 0, 64]);
+
+    let json = registers_json(&instance, &outcome);
+    assert!(json.contains("\"mode\": \"chip8\""));
+    assert!(json.contains("\"stop\": \"frames\""));
+    assert!(json.contains("\"v\": [0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]"));
+    assert!(json.contains("\"stack\": []"));
+}
+
+#[test]
+fn run_stops_on_errors() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.load_rom_bytes(&[0x00, 0xEE]).unwrap();
+    let outcome = run(&mut instance, 10, 5, None, &[]);
+    assert_eq!(outcome.frames, 1);
+    assert_eq!(
+        outcome.stop,
+        Stop::Error("Tried to return with an empty stack".to_string())
+    );
+    let json = registers_json(&instance, &outcome);
+    assert!(json.ends_with("\"error\": \"Tried to return with an empty stack\"\n}\n"));
+}
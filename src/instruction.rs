@@ -0,0 +1,289 @@
+// The instruction set: opcodes decoded to instructions, shared by the interpreter, the
+// debugger and the disassembler.
+// x and y are register numbers, kk a byte, nnn an address and n a nibble
+use crate::chip8::Mode;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Instruction {
+    // 00e0: clear the display
+    Cls,
+    // 00ee: return from a subroutine
+    Ret,
+    // 00cn: scroll the display n pixels down (SUPER-CHIP)
+    ScrollDown(u8),
+    // 00dn: scroll the display n pixels up (XO-CHIP)
+    ScrollUp(u8),
+    // 00fb: scroll the display 4 pixels right (SUPER-CHIP)
+    ScrollRight,
+    // 00fc: scroll the display 4 pixels left (SUPER-CHIP)
+    ScrollLeft,
+    // 00fd: exit the interpreter (SUPER-CHIP)
+    Exit,
+    // 00fe: 64x32 display (SUPER-CHIP)
+    Lores,
+    // 00ff: 128x64 display (SUPER-CHIP)
+    Hires,
+    // 1nnn: jump to nnn
+    Jump(u16),
+    // 2nnn: call the subroutine at nnn
+    Call(u16),
+    // 3xkk: skip the next instruction if vx == kk
+    SkipEqImm(u8, u8),
+    // 4xkk: skip the next instruction if vx != kk
+    SkipNeImm(u8, u8),
+    // 5xy0: skip the next instruction if vx == vy
+    SkipEq(u8, u8),
+    // 5xy2: store vx through vy at I (XO-CHIP)
+    StoreRange(u8, u8),
+    // 5xy3: load vx through vy from I (XO-CHIP)
+    LoadRange(u8, u8),
+    // 6xkk: vx = kk
+    LoadImm(u8, u8),
+    // 7xkk: vx += kk
+    AddImm(u8, u8),
+    // 8xy0: vx = vy
+    Move(u8, u8),
+    // 8xy1: vx |= vy
+    Or(u8, u8),
+    // 8xy2: vx &= vy
+    And(u8, u8),
+    // 8xy3: vx ^= vy
+    Xor(u8, u8),
+    // 8xy4: vx += vy, vf = carry
+    Add(u8, u8),
+    // 8xy5: vx -= vy, vf = not borrow
+    Sub(u8, u8),
+    // 8xy6: shift right, vf = the bit shifted out
+    ShiftRight(u8, u8),
+    // 8xy7: vx = vy - vx, vf = not borrow
+    SubN(u8, u8),
+    // 8xye: shift left, vf = the bit shifted out
+    ShiftLeft(u8, u8),
+    // 9xy0: skip the next instruction if vx != vy
+    SkipNe(u8, u8),
+    // annn: I = nnn
+    LoadI(u16),
+    // bnnn: jump to nnn + v0 (or vx with the jump quirk)
+    JumpOffset(u8, u16),
+    // cxkk: vx = random & kk
+    Random(u8, u8),
+    // dxyn: draw the n rows sprite at I at (vx, vy). dxy0 draws 16x16 on SUPER-CHIP
+    Draw(u8, u8, u8),
+    // ex9e: skip the next instruction if the key vx is pressed
+    SkipKey(u8),
+    // exa1: skip the next instruction if the key vx isn't pressed
+    SkipNotKey(u8),
+    // f000 nnnn: I = the 16 bit address following the opcode (XO-CHIP)
+    LoadILong,
+    // fn01: select the drawing planes (XO-CHIP)
+    Plane(u8),
+    // f002: load the audio pattern from I (XO-CHIP)
+    Audio,
+    // fx07: vx = delay timer
+    GetDelay(u8),
+    // fx0a: wait for a key, store it in vx
+    WaitKey(u8),
+    // fx15: delay timer = vx
+    SetDelay(u8),
+    // fx18: sound timer = vx
+    SetSound(u8),
+    // fx1e: I += vx
+    AddI(u8),
+    // fx29: I = the font digit vx
+    Font(u8),
+    // fx30: I = the big font digit vx (SUPER-CHIP)
+    BigFont(u8),
+    // fx33: store the decimal digits of vx at I
+    Bcd(u8),
+    // fx3a: audio pattern pitch = vx (XO-CHIP)
+    Pitch(u8),
+    // fx55: store v0 through vx at I
+    StoreRegisters(u8),
+    // fx65: load v0 through vx from I
+    LoadRegisters(u8),
+    // fx75: store v0 through vx in the RPL flags (SUPER-CHIP)
+    StoreFlags(u8),
+    // fx85: load v0 through vx from the RPL flags (SUPER-CHIP)
+    LoadFlags(u8),
+    // not an instruction
+    Unknown(u16),
+}
+
+pub fn decode(opcode: u16) -> Instruction {
+    let nibbles = (
+        (opcode & 0xF000) >> 12,
+        (opcode & 0x0F00) >> 8,
+        (opcode & 0x00F0) >> 4,
+        opcode & 0x000F,
+    );
+    let nnn = opcode & 0x0FFF;
+    let kk = (opcode & 0x00FF) as u8;
+    let n = (opcode & 0x000F) as u8;
+    let x = ((opcode >> 8) & 0x000F) as u8;
+    let y = ((opcode >> 4) & 0x000F) as u8;
+    match nibbles {
+        (0x0, 0x0, 0xE, 0x0) => Instruction::Cls,
+        (0x0, 0x0, 0xE, 0xE) => Instruction::Ret,
+        (0x0, 0x0, 0xC, _) => Instruction::ScrollDown(n),
+        (0x0, 0x0, 0xD, _) => Instruction::ScrollUp(n),
+        (0x0, 0x0, 0xF, 0xB) => Instruction::ScrollRight,
+        (0x0, 0x0, 0xF, 0xC) => Instruction::ScrollLeft,
+        (0x0, 0x0, 0xF, 0xD) => Instruction::Exit,
+        (0x0, 0x0, 0xF, 0xE) => Instruction::Lores,
+        (0x0, 0x0, 0xF, 0xF) => Instruction::Hires,
+        (0x1, _, _, _) => Instruction::Jump(nnn),
+        (0x2, _, _, _) => Instruction::Call(nnn),
+        (0x3, _, _, _) => Instruction::SkipEqImm(x, kk),
+        (0x4, _, _, _) => Instruction::SkipNeImm(x, kk),
+        (0x5, _, _, 0x0) => Instruction::SkipEq(x, y),
+        (0x5, _, _, 0x2) => Instruction::StoreRange(x, y),
+        (0x5, _, _, 0x3) => Instruction::LoadRange(x, y),
+        (0x6, _, _, _) => Instruction::LoadImm(x, kk),
+        (0x7, _, _, _) => Instruction::AddImm(x, kk),
+        (0x8, _, _, 0x0) => Instruction::Move(x, y),
+        (0x8, _, _, 0x1) => Instruction::Or(x, y),
+        (0x8, _, _, 0x2) => Instruction::And(x, y),
+        (0x8, _, _, 0x3) => Instruction::Xor(x, y),
+        (0x8, _, _, 0x4) => Instruction::Add(x, y),
+        (0x8, _, _, 0x5) => Instruction::Sub(x, y),
+        (0x8, _, _, 0x6) => Instruction::ShiftRight(x, y),
+        (0x8, _, _, 0x7) => Instruction::SubN(x, y),
+        (0x8, _, _, 0xE) => Instruction::ShiftLeft(x, y),
+        (0x9, _, _, 0x0) => Instruction::SkipNe(x, y),
+        (0xA, _, _, _) => Instruction::LoadI(nnn),
+        (0xB, _, _, _) => Instruction::JumpOffset(x, nnn),
+        (0xC, _, _, _) => Instruction::Random(x, kk),
+        (0xD, _, _, _) => Instruction::Draw(x, y, n),
+        (0xE, _, 0x9, 0xE) => Instruction::SkipKey(x),
+        (0xE, _, 0xA, 0x1) => Instruction::SkipNotKey(x),
+        (0xF, 0x0, 0x0, 0x0) => Instruction::LoadILong,
+        (0xF, _, 0x0, 0x1) => Instruction::Plane(x),
+        (0xF, 0x0, 0x0, 0x2) => Instruction::Audio,
+        (0xF, _, 0x0, 0x7) => Instruction::GetDelay(x),
+        (0xF, _, 0x0, 0xA) => Instruction::WaitKey(x),
+        (0xF, _, 0x1, 0x5) => Instruction::SetDelay(x),
+        (0xF, _, 0x1, 0x8) => Instruction::SetSound(x),
+        (0xF, _, 0x1, 0xE) => Instruction::AddI(x),
+        (0xF, _, 0x2, 0x9) => Instruction::Font(x),
+        (0xF, _, 0x3, 0x0) => Instruction::BigFont(x),
+        (0xF, _, 0x3, 0x3) => Instruction::Bcd(x),
+        (0xF, _, 0x3, 0xA) => Instruction::Pitch(x),
+        (0xF, _, 0x5, 0x5) => Instruction::StoreRegisters(x),
+        (0xF, _, 0x6, 0x5) => Instruction::LoadRegisters(x),
+        (0xF, _, 0x7, 0x5) => Instruction::StoreFlags(x),
+        (0xF, _, 0x8, 0x5) => Instruction::LoadFlags(x),
+        _ => Instruction::Unknown(opcode),
+    }
+}
+
+// The opcode of an instruction, the inverse of decode
+pub fn encode(instruction: Instruction) -> u16 {
+    let xkk = |x: u8, kk: u8| (x as u16) << 8 | kk as u16;
+    let xyn = |x: u8, y: u8, n: u8| (x as u16) << 8 | (y as u16) << 4 | n as u16;
+    let x = |x: u8| (x as u16) << 8;
+    match instruction {
+        Instruction::Cls => 0x00E0,
+        Instruction::Ret => 0x00EE,
+        Instruction::ScrollDown(n) => 0x00C0 | n as u16,
+        Instruction::ScrollUp(n) => 0x00D0 | n as u16,
+        Instruction::ScrollRight => 0x00FB,
+        Instruction::ScrollLeft => 0x00FC,
+        Instruction::Exit => 0x00FD,
+        Instruction::Lores => 0x00FE,
+        Instruction::Hires => 0x00FF,
+        Instruction::Jump(nnn) => 0x1000 | nnn,
+        Instruction::Call(nnn) => 0x2000 | nnn,
+        Instruction::SkipEqImm(vx, kk) => 0x3000 | xkk(vx, kk),
+        Instruction::SkipNeImm(vx, kk) => 0x4000 | xkk(vx, kk),
+        Instruction::SkipEq(vx, vy) => 0x5000 | xyn(vx, vy, 0x0),
+        Instruction::StoreRange(vx, vy) => 0x5000 | xyn(vx, vy, 0x2),
+        Instruction::LoadRange(vx, vy) => 0x5000 | xyn(vx, vy, 0x3),
+        Instruction::LoadImm(vx, kk) => 0x6000 | xkk(vx, kk),
+        Instruction::AddImm(vx, kk) => 0x7000 | xkk(vx, kk),
+        Instruction::Move(vx, vy) => 0x8000 | xyn(vx, vy, 0x0),
+        Instruction::Or(vx, vy) => 0x8000 | xyn(vx, vy, 0x1),
+        Instruction::And(vx, vy) => 0x8000 | xyn(vx, vy, 0x2),
+        Instruction::Xor(vx, vy) => 0x8000 | xyn(vx, vy, 0x3),
+        Instruction::Add(vx, vy) => 0x8000 | xyn(vx, vy, 0x4),
+        Instruction::Sub(vx, vy) => 0x8000 | xyn(vx, vy, 0x5),
+        Instruction::ShiftRight(vx, vy) => 0x8000 | xyn(vx, vy, 0x6),
+        Instruction::SubN(vx, vy) => 0x8000 | xyn(vx, vy, 0x7),
+        Instruction::ShiftLeft(vx, vy) => 0x8000 | xyn(vx, vy, 0xE),
+        Instruction::SkipNe(vx, vy) => 0x9000 | xyn(vx, vy, 0x0),
+        Instruction::LoadI(nnn) => 0xA000 | nnn,
+        // the register is already part of nnn
+        Instruction::JumpOffset(_, nnn) => 0xB000 | nnn,
+        Instruction::Random(vx, kk) => 0xC000 | xkk(vx, kk),
+        Instruction::Draw(vx, vy, n) => 0xD000 | xyn(vx, vy, n),
+        Instruction::SkipKey(vx) => 0xE09E | x(vx),
+        Instruction::SkipNotKey(vx) => 0xE0A1 | x(vx),
+        Instruction::LoadILong => 0xF000,
+        Instruction::Plane(n) => 0xF001 | x(n),
+        Instruction::Audio => 0xF002,
+        Instruction::GetDelay(vx) => 0xF007 | x(vx),
+        Instruction::WaitKey(vx) => 0xF00A | x(vx),
+        Instruction::SetDelay(vx) => 0xF015 | x(vx),
+        Instruction::SetSound(vx) => 0xF018 | x(vx),
+        Instruction::AddI(vx) => 0xF01E | x(vx),
+        Instruction::Font(vx) => 0xF029 | x(vx),
+        Instruction::BigFont(vx) => 0xF030 | x(vx),
+        Instruction::Bcd(vx) => 0xF033 | x(vx),
+        Instruction::Pitch(vx) => 0xF03A | x(vx),
+        Instruction::StoreRegisters(vx) => 0xF055 | x(vx),
+        Instruction::LoadRegisters(vx) => 0xF065 | x(vx),
+        Instruction::StoreFlags(vx) => 0xF075 | x(vx),
+        Instruction::LoadFlags(vx) => 0xF085 | x(vx),
+        Instruction::Unknown(opcode) => opcode,
+    }
+}
+
+impl Instruction {
+    // false for the instructions of the later interpreters, and for unknown opcodes
+    pub fn supported_by(self, mode: Mode) -> bool {
+        match self {
+            Instruction::ScrollDown(_)
+            | Instruction::ScrollRight
+            | Instruction::ScrollLeft
+            | Instruction::Exit
+            | Instruction::Lores
+            | Instruction::Hires
+            | Instruction::BigFont(_)
+            | Instruction::StoreFlags(_)
+            | Instruction::LoadFlags(_) => mode != Mode::Chip8,
+            Instruction::ScrollUp(_)
+            | Instruction::StoreRange(..)
+            | Instruction::LoadRange(..)
+            | Instruction::LoadILong
+            | Instruction::Plane(_)
+            | Instruction::Audio
+            | Instruction::Pitch(_) => mode == Mode::XoChip,
+            Instruction::Unknown(_) => false,
+            _ => true,
+        }
+    }
+
+    // in bytes, f000 is followed by its 16 bit operand
+    pub fn size(self) -> usize {
+        match self {
+            Instruction::LoadILong => 4,
+            _ => 2,
+        }
+    }
+
+    // true for the instructions skipping the next one on a condition
+    pub fn is_skip(self) -> bool {
+        matches!(
+            self,
+            Instruction::SkipEqImm(..)
+                | Instruction::SkipNeImm(..)
+                | Instruction::SkipEq(..)
+                | Instruction::SkipNe(..)
+                | Instruction::SkipKey(_)
+                | Instruction::SkipNotKey(_)
+        )
+    }
+}
+
+#[cfg(test)]
+#[path = "./instruction_tests.rs"]
+mod instruction_tests;
@@ -0,0 +1,38 @@
+use super::*;
+
+#[test]
+fn decode_fields() {
+    assert_eq!(decode(0x00E0), Instruction::Cls);
+    assert_eq!(decode(0x1ABC), Instruction::Jump(0xABC));
+    assert_eq!(decode(0x3A12), Instruction::SkipEqImm(0xA, 0x12));
+    assert_eq!(decode(0x8AB6), Instruction::ShiftRight(0xA, 0xB));
+    assert_eq!(decode(0xB3FF), Instruction::JumpOffset(0x3, 0x3FF));
+    assert_eq!(decode(0xD125), Instruction::Draw(0x1, 0x2, 5));
+    assert_eq!(decode(0xF201), Instruction::Plane(2));
+    assert_eq!(decode(0xF765), Instruction::LoadRegisters(7));
+}
+
+#[test]
+fn decode_unknown() {
+    for opcode in [0x0123, 0x5AB1, 0x8AB8, 0x9AB1, 0xE19F, 0xF102, 0xF1FF] {
+        assert_eq!(decode(opcode), Instruction::Unknown(opcode));
+    }
+}
+
+#[test]
+fn supported_by_mode() {
+    assert!(decode(0x00E0).supported_by(Mode::Chip8));
+    assert!(!decode(0x00FF).supported_by(Mode::Chip8));
+    assert!(decode(0x00FF).supported_by(Mode::SuperChip));
+    assert!(!decode(0xF000).supported_by(Mode::SuperChip));
+    assert!(decode(0xF000).supported_by(Mode::XoChip));
+    assert!(!decode(0x0123).supported_by(Mode::XoChip));
+    assert_eq!(decode(0xF000).size(), 4);
+}
+
+#[test]
+fn encode_is_decode_inverse() {
+    for opcode in 0..=0xFFFF {
+        assert_eq!(encode(decode(opcode)), opcode, "{:#06X}", opcode);
+    }
+}
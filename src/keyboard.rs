@@ -1,65 +1,128 @@
-use crate::chip8;
-use sdl2::event::Event;
+use chip8_emulator::chip8::NUM_KEYS;
+use chip8_emulator::config::{Binding, Hotkey};
+use chip8_emulator::Chip8;
+use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
+use std::collections::HashMap;
 
 pub struct InputDevice {
     events: sdl2::EventPump,
+    // the keyboard keys of the CHIP-8 keypad
+    keys: HashMap<Keycode, u8>,
+    hotkeys: HashMap<Keycode, Hotkey>,
+    // the rewind key is held
+    rewinding: bool,
+    // set when the window must be drawn again
+    redraw: bool,
+}
+
+// SDL's name of a key named in the config file
+fn sdl_name(name: &str) -> String {
+    let name = match name {
+        "Minus" => "-",
+        "Equals" => "=",
+        "Comma" => ",",
+        "Period" => ".",
+        "Semicolon" => ";",
+        "Quote" => "'",
+        "Slash" => "/",
+        "Backslash" => "\\",
+        "LeftBracket" => "[",
+        "RightBracket" => "]",
+        "Backquote" => "`",
+        _ => match name.strip_prefix("Keypad") {
+            Some(digit) => return format!("Keypad {}", digit),
+            None => name,
+        },
+    };
+    name.to_string()
 }
 
 impl InputDevice {
-    pub fn new(sdl_context: &sdl2::Sdl) -> InputDevice {
+    pub fn new(sdl_context: &sdl2::Sdl, bindings: &[(String, Binding)]) -> InputDevice {
+        let mut keys = HashMap::new();
+        let mut hotkeys = HashMap::new();
+        for (name, binding) in bindings {
+            let keycode = Keycode::from_name(&sdl_name(name))
+                .unwrap_or_else(|| panic!("No SDL key named {}", name));
+            match *binding {
+                Binding::Key(key) => {
+                    keys.insert(keycode, key);
+                }
+                Binding::Hotkey(hotkey) => {
+                    hotkeys.insert(keycode, hotkey);
+                }
+            }
+        }
         InputDevice {
             events: sdl_context
                 .event_pump()
                 .expect("Something went wrong with the event pump"),
+            keys,
+            hotkeys,
+            rewinding: false,
+            redraw: false,
         }
     }
-    // handles the input from the window. returns true of needs to exit
-    pub fn handle_input(&mut self, chip8: &mut chip8::Chip8) -> bool{
-        for i in &mut chip8.keyboard {
-            *i = false;
-        }
-
+    // Handles the events of the window: the keypad keys are pressed and released on the machine,
+    // when given. Returns the hotkeys that were pressed, and Rewind while its key is held
+    pub fn handle_input(&mut self, mut keypad: Option<&mut Chip8>) -> Vec<Hotkey> {
+        let mut hotkeys = Vec::new();
         for event in self.events.poll_iter() {
             match event {
-                Event::Quit {..} |
-                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
-                    return true
-                },
+                Event::Quit { .. } => hotkeys.push(Hotkey::Quit),
+                // the key ups would be missed, nothing stays held
+                Event::Window {
+                    win_event: WindowEvent::FocusLost,
+                    ..
+                } => {
+                    if let Some(chip8) = keypad.as_deref_mut() {
+                        for key in 0..NUM_KEYS {
+                            chip8.key_up(key);
+                        }
+                    }
+                    self.rewinding = false;
+                }
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..) | WindowEvent::Exposed,
+                    ..
+                } => self.redraw = true,
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } => {
+                    if let (Some(&key), Some(chip8)) = (self.keys.get(&keycode), &mut keypad) {
+                        chip8.key_down(key as usize);
+                    }
+                    match self.hotkeys.get(&keycode) {
+                        Some(Hotkey::Rewind) => self.rewinding = true,
+                        Some(&hotkey) => hotkeys.push(hotkey),
+                        None => {}
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(keycode),
+                    ..
+                } => {
+                    if let (Some(&key), Some(chip8)) = (self.keys.get(&keycode), &mut keypad) {
+                        chip8.key_up(key as usize);
+                    }
+                    if self.hotkeys.get(&keycode) == Some(&Hotkey::Rewind) {
+                        self.rewinding = false;
+                    }
+                }
                 _ => {}
             }
         }
-        let keys: Vec<Keycode> = self
-            .events
-            .keyboard_state()
-            .pressed_scancodes()
-            .filter_map(Keycode::from_scancode)
-            .collect();
-        // https://github.com/starrhorne/chip8-rust/blob/master/src/drivers/input_driver.rs
-        for key in keys {
-            let index = match key {
-                Keycode::Num1 => Some(0x1),
-                Keycode::Num2 => Some(0x2),
-                Keycode::Num3 => Some(0x3),
-                Keycode::Num4 => Some(0xc),
-                Keycode::Q => Some(0x4),
-                Keycode::W => Some(0x5),
-                Keycode::E => Some(0x6),
-                Keycode::R => Some(0xd),
-                Keycode::A => Some(0x7),
-                Keycode::S => Some(0x8),
-                Keycode::D => Some(0x9),
-                Keycode::F => Some(0xe),
-                Keycode::Z => Some(0xa),
-                Keycode::X => Some(0x0),
-                Keycode::C => Some(0xb),
-                Keycode::V => Some(0xf),
-                _ => None,
-            };
-            if let Some(i) = index {
-                chip8.keyboard[i] = true;
-            }
+        if self.rewinding {
+            hotkeys.push(Hotkey::Rewind);
         }
-        false
+        hotkeys
+    }
+
+    // whether the window was resized or uncovered since the last call
+    pub fn take_redraw(&mut self) -> bool {
+        std::mem::take(&mut self.redraw)
     }
 }
@@ -0,0 +1,32 @@
+// The emulator core: the machine itself, with no dependency on a frontend.
+// The SDL2 frontend is the chip8-emulator binary (the "sdl" feature).
+pub mod asm;
+mod binary;
+pub mod chip8;
+pub mod config;
+pub mod debugger;
+pub mod disasm;
+pub mod error;
+pub mod framebuffer;
+pub mod headless;
+pub mod instruction;
+pub mod movie;
+pub mod palette;
+pub mod persistence;
+pub mod png;
+pub mod quirks;
+pub mod rewind;
+pub mod rng;
+pub mod trace;
+pub mod tui;
+pub mod viewport;
+
+pub use crate::chip8::{Access, Chip8, MemoryObserver, Mode};
+pub use crate::debugger::Debugger;
+pub use crate::error::Chip8Error;
+pub use crate::framebuffer::Framebuffer;
+pub use crate::instruction::Instruction;
+pub use crate::movie::Movie;
+pub use crate::palette::Palette;
+pub use crate::quirks::Quirks;
+pub use crate::rewind::Rewinder;
@@ -1,51 +1,470 @@
-mod chip8;
+#[cfg(feature = "sdl")]
 mod display;
+#[cfg(feature = "sdl")]
 mod keyboard;
+#[cfg(feature = "sdl")]
 mod beep;
-use chip8::{Chip8};
+#[cfg(feature = "sdl")]
+mod window;
+#[cfg(unix)]
+mod terminal;
+mod frontend;
+mod options;
+use chip8_emulator::config::{Binding, Config, Hotkey};
+use chip8_emulator::movie::{self, Player, Recorder};
+use chip8_emulator::palette::{self, Palette};
+use chip8_emulator::persistence::{Flicker, Persistence};
+use chip8_emulator::debugger::{self, Command};
+use chip8_emulator::trace::Tracer;
+use chip8_emulator::tui::Glyphs;
+use chip8_emulator::headless;
+use chip8_emulator::{rewind, Chip8, Chip8Error, Debugger, Movie, Rewinder};
+use frontend::Frontend;
+use options::Options;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufWriter, Write};
 use std::process;
-use std::time::Duration;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
 use std::env;
 
+// the timers and the display run at 60hz
+const FRAME_DURATION: Duration = Duration::from_micros(1_000_000 / 60);
+// when the emulator falls further behind than this, it stops trying to catch up
+const MAX_FRAME_LAG: Duration = Duration::from_millis(100);
+// save state slots 0 - 9
+const NUM_SLOTS: u8 = 10;
+
+// the size of the pixels in the screenshots
+const SCREENSHOT_SCALE: usize = 4;
+
+// save states are stored next to the ROM: <rom>.state<slot>
+fn state_path(rom: &str, slot: u8) -> String {
+    format!("{}.state{}", rom, slot)
+}
+
+// screenshots too, numbered from 1 without overwriting the older ones: <rom>.<n>.png
+fn screenshot_path(rom: &str) -> String {
+    (1..)
+        .map(|n| format!("{}.{}.png", rom, n))
+        .find(|path| !std::path::Path::new(path).exists())
+        .unwrap()
+}
+
+// The lines typed in the terminal. stdin is read on its own thread, so that waiting for a
+// command doesn't freeze the window
+fn read_commands() -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            // stop when stdin closes or the emulator exits
+            let sent = line.map(|line| sender.send(line).is_ok());
+            if !sent.unwrap_or(false) {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+// the window, with the [display] settings
+#[cfg(feature = "sdl")]
+fn open_window(
+    options: &Options,
+    config: &Config,
+    palette: Palette,
+    persistence: Persistence,
+    bindings: &[(String, Binding)],
+) -> Result<Box<dyn Frontend>, String> {
+    use chip8_emulator::viewport::Scaling;
+    Ok(Box::new(window::Window::open(
+        palette,
+        options.scale.or(config.scale).unwrap_or(display::DEFAULT_SCALE),
+        options.scaling.or(config.scaling).unwrap_or(Scaling::Integer),
+        options.border.or(config.border),
+        persistence,
+        bindings,
+    )))
+}
+
+#[cfg(not(feature = "sdl"))]
+fn open_window(
+    _: &Options,
+    _: &Config,
+    _: Palette,
+    _: Persistence,
+    _: &[(String, Binding)],
+) -> Result<Box<dyn Frontend>, String> {
+    Err("Built without the window (the sdl feature), run it in the terminal with --tui".to_string())
+}
+
+#[cfg(unix)]
+fn open_terminal(
+    glyphs: Glyphs,
+    palette: Palette,
+    persistence: Persistence,
+    bindings: &[(String, Binding)],
+) -> Result<Box<dyn Frontend>, String> {
+    let terminal = terminal::Terminal::open(glyphs, palette, persistence, bindings)?;
+    Ok(Box::new(terminal))
+}
+
+#[cfg(not(unix))]
+fn open_terminal(
+    _: Glyphs,
+    _: Palette,
+    _: Persistence,
+    _: &[(String, Binding)],
+) -> Result<Box<dyn Frontend>, String> {
+    Err("The terminal frontend needs a Unix terminal".to_string())
+}
+
+// show the debugger output, then wait for the next command
+fn prompt(output: &str) {
+    if !output.is_empty() {
+        println!("{}", output);
+    }
+    print!("(chip8) ");
+    let _ = io::stdout().flush();
+}
+
 fn main() {
     println!("CHIP-8 emulator starting...");
-    let args: Vec<String> = env::args().collect();
-    let mut display = display::Display::initialize();
-    let mut keyboard = keyboard::InputDevice::new(&display.context);
-    let audio = beep::AudioDevice::new(&display.context);
-    
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, options::USAGE);
+            process::exit(1);
+        }
+    };
+    let rom = match fs::read(&options.rom) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("Couldn't load ROM: {}", e);
+            process::exit(1);
+        }
+    };
+    // a movie replaces the options it was recorded with
+    let mut player = options.play.as_ref().map(|path| {
+        let player = Movie::load(path).and_then(|movie| Player::new(movie, &rom));
+        match player {
+            Ok(player) => player,
+            Err(e) => {
+                eprintln!("Couldn't play movie {}: {}", path, e);
+                process::exit(1);
+            }
+        }
+    });
+    if let Some(player) = &player {
+        let movie = player.movie();
+        options.seed = movie.seed;
+        options.mode = movie.mode;
+        options.quirks = movie.quirks;
+        options.ipf = movie.ipf;
+    }
+
+    let config = match &options.config {
+        Some(path) => Config::load(path).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
+        }),
+        None => Config::default(),
+    };
+    // the hash selects the [rom.<hash>] section of the config
+    let rom_hash = movie::hash(&rom);
+    println!("ROM hash: {:016X}", rom_hash);
+
+    // the presets the hotkey switches between, plus the palette chosen when it's custom
+    let mut palettes: Vec<Palette> = palette::PRESETS.iter().map(|&(_, palette)| palette).collect();
+    let start = options.palette.or(config.palette).unwrap_or_default();
+    let position = palettes.iter().position(|&palette| palette == start);
+    let mut palette_index = position.unwrap_or_else(|| {
+        palettes.insert(0, start);
+        0
+    });
+
+    let flicker = options.flicker.or(config.flicker).unwrap_or(Flicker::Off);
+    let strength = options
+        .flicker_strength
+        .or(config.flicker_strength)
+        .unwrap_or_else(|| flicker.default_strength());
+    let persistence = Persistence::new(flicker, strength);
+    // the same bindings in the window and in the terminal
+    let bindings = config.bindings(rom_hash);
+
     // create chip8 instance
-    let mut chip8 = Chip8::new();
-    let rom = &args[1];
-    if let Err(e) = chip8.load_rom(rom) {
+    // the seed is shown so that a run can be reproduced with --seed
+    println!("Random seed: {}", options.seed);
+    let mut chip8 = Chip8::new_with_seed(options.quirks, options.seed);
+    chip8.set_mode(options.mode);
+    if let Err(e) = chip8.load_rom_bytes(&rom) {
         eprintln!("Couldn't load ROM: {}", e);
 
         process::exit(1);
     }
+    // the machine as it starts, for resetting
+    let initial_state = chip8.save_state();
+    let mut recorder = options
+        .record
+        .as_ref()
+        .map(|_| Recorder::new(&chip8, &rom, options.seed, options.ipf));
+    // rewinding, loading a state, resetting or changing the speed would make the movie
+    // impossible to replay
+    let movie_active = recorder.is_some() || player.is_some();
+    // a desync is only reported once
+    let mut desynced = false;
+    let mut tracer = options.trace.as_ref().map(|path| {
+        let tracer = File::create(path).and_then(|file| {
+            let range = options.trace_range.clone();
+            Tracer::new(BufWriter::new(file), options.trace_format, range)
+        });
+        match tracer {
+            Ok(tracer) => tracer,
+            Err(e) => {
+                eprintln!("Couldn't write trace {}: {}", path, e);
+                process::exit(1);
+            }
+        }
+    });
+    let frontend = match options.tui {
+        Some(glyphs) => open_terminal(glyphs, start, persistence, &bindings),
+        None => open_window(&options, &config, start, persistence, &bindings),
+    };
+    let mut frontend = frontend.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    let mut debugger = if options.debug {
+        println!("{}", debugger::HELP);
+        prompt("");
+        Some((Debugger::new(), read_commands()))
+    } else {
+        None
+    };
 
+    // when the next frame is due
+    let mut next_frame = Instant::now();
+    // the save state slot used by the hotkeys
+    let mut slot = 0;
+    // the last minute of frames, for rewinding
+    let mut rewinder = Rewinder::new(rewind::DEFAULT_CAPACITY);
+    // paused with the hotkey
+    let mut pause = false;
+    // stopped by an execution error, until the machine is reset, rewound or loaded
+    let mut faulted = false;
     'main: loop {
-        if keyboard.handle_input(&mut chip8) {
+        let mut rewinding = false;
+        // the movie replaces the keyboard
+        let keypad = if player.is_none() { Some(&mut chip8) } else { None };
+        for hotkey in frontend.handle_input(keypad) {
+            match hotkey {
+                Hotkey::Quit => break 'main,
+                Hotkey::Rewind if movie_active => {}
+                Hotkey::Rewind => rewinding = true,
+                Hotkey::Pause => {
+                    pause = !pause;
+                    frontend.message(if pause { "Paused" } else { "Resumed" });
+                }
+                Hotkey::Reset if movie_active => {
+                    frontend.message("The ROM can't be reset while a movie is recording or playing")
+                }
+                Hotkey::Reset => {
+                    if let Err(e) = chip8.load_state(&initial_state) {
+                        frontend.error(&format!("Couldn't reset: {}", e));
+                    }
+                    rewinder.clear();
+                    faulted = false;
+                    frontend.message("Reset");
+                }
+                Hotkey::SpeedUp | Hotkey::SpeedDown if movie_active => {
+                    frontend.message("The speed can't change while a movie is recording or playing")
+                }
+                Hotkey::SpeedUp => {
+                    options.ipf = options.ipf.saturating_mul(2);
+                    frontend.message(&format!("Instructions per frame: {}", options.ipf));
+                }
+                Hotkey::SpeedDown => {
+                    options.ipf = (options.ipf / 2).max(1);
+                    frontend.message(&format!("Instructions per frame: {}", options.ipf));
+                }
+                Hotkey::NextPalette => {
+                    palette_index = (palette_index + 1) % palettes.len();
+                    let palette = palettes[palette_index];
+                    frontend.set_palette(palette);
+                    frontend.message(&format!("Palette {}", palette.name()));
+                    if let Err(e) = frontend.draw(&chip8) {
+                        frontend.error(&format!("Draw error: {}", e));
+                    }
+                }
+                Hotkey::Fullscreen => {
+                    let shown = frontend
+                        .toggle_fullscreen()
+                        .and_then(|()| frontend.draw(&chip8));
+                    if let Err(e) = shown {
+                        frontend.error(&format!("Fullscreen error: {}", e));
+                    }
+                }
+                Hotkey::Screenshot => {
+                    let path = screenshot_path(&options.rom);
+                    let screen = chip8.framebuffer();
+                    let image = headless::png(screen, SCREENSHOT_SCALE, &palettes[palette_index]);
+                    match fs::write(&path, image) {
+                        Ok(()) => frontend.message(&format!("Saved screenshot to {}", path)),
+                        Err(e) => {
+                            frontend.error(&format!("Couldn't save screenshot to {}: {}", path, e))
+                        }
+                    }
+                }
+                Hotkey::SaveState => {
+                    let path = state_path(&options.rom, slot);
+                    match fs::write(&path, chip8.save_state()) {
+                        Ok(()) => frontend.message(&format!("Saved state to {}", path)),
+                        Err(e) => {
+                            frontend.error(&format!("Couldn't save state to {}: {}", path, e))
+                        }
+                    }
+                }
+                Hotkey::LoadState if movie_active => {
+                    frontend.message("States can't be loaded while a movie is recording or playing")
+                }
+                Hotkey::LoadState => {
+                    let path = state_path(&options.rom, slot);
+                    let result = fs::read(&path)
+                        .map_err(|e| e.to_string())
+                        .and_then(|state| chip8.load_state(&state).map_err(|e| e.to_string()));
+                    match result {
+                        Ok(()) => {
+                            faulted = false;
+                            frontend.message(&format!("Loaded state from {}", path))
+                        }
+                        Err(e) => {
+                            frontend.error(&format!("Couldn't load state from {}: {}", path, e))
+                        }
+                    }
+                }
+                Hotkey::PreviousSlot => {
+                    slot = (slot + NUM_SLOTS - 1) % NUM_SLOTS;
+                    frontend.message(&format!("Save state slot {}", slot));
+                }
+                Hotkey::NextSlot => {
+                    slot = (slot + 1) % NUM_SLOTS;
+                    frontend.message(&format!("Save state slot {}", slot));
+                }
+            }
+        }
+        if chip8.exited() {
             break 'main;
         }
-        // execute a cpu cycle (one instruction)
-        if let Err(e) = chip8.cycle() {
-            eprintln!("Execution error: {}", e)
+        if let Some((debugger, commands)) = &mut debugger {
+            for line in commands.try_iter() {
+                if line.trim().is_empty() {
+                    prompt("");
+                    continue;
+                }
+                match Command::parse(&line) {
+                    Ok(Command::Quit) => break 'main,
+                    Ok(command) => prompt(&debugger.execute(&command, &mut chip8)),
+                    Err(e) => prompt(&e),
+                }
+            }
+        }
+        // the whole machine stands still while paused, by the hotkey or the debugger, or after an
+        // error
+        let paused = pause
+            || faulted
+            || debugger
+                .as_ref()
+                .is_some_and(|(debugger, _)| debugger.paused());
+        if let (Some(player), false) = (&mut player, paused) {
+            if !player.apply_input(&mut chip8) {
+                frontend.message("Movie finished");
+                break 'main;
+            }
+        }
+        if rewinding {
+            // go back one frame instead of running one
+            if rewinder.step_back(&mut chip8) {
+                faulted = false;
+            }
+        } else if !paused {
+            if let Some(recorder) = &mut recorder {
+                recorder.record_input(&chip8);
+            }
+            // execute a frame worth of instructions
+            if let Some((debugger, _)) = &mut debugger {
+                if let Some(stop) = debugger.run_frame(&mut chip8, options.ipf) {
+                    prompt(&stop);
+                }
+            } else if let Some(active) = &mut tracer {
+                match active.run_frame(&mut chip8, options.ipf) {
+                    Ok(()) => {}
+                    Err(Chip8Error::Io(e)) => {
+                        let message = format!("Couldn't write the trace, tracing stopped: {}", e);
+                        frontend.error(&message);
+                        tracer = None;
+                    }
+                    Err(e) => {
+                        frontend.error(&format!("Execution error, stopped: {}", e));
+                        faulted = true;
+                    }
+                }
+            } else if let Err(e) = chip8.run_frame(options.ipf) {
+                frontend.error(&format!("Execution error, stopped: {}", e));
+                faulted = true;
+            }
+            rewinder.push(&chip8);
+            if let Some(recorder) = &mut recorder {
+                recorder.record_frame(&chip8);
+            }
+            if let Some(player) = &mut player {
+                if let Err(e) = player.verify(&chip8) {
+                    if !desynced {
+                        frontend.error(&e.to_string());
+                        desynced = true;
+                    }
+                }
+            }
         }
 
-        if chip8.draw {
-            if let Err(e) = display.draw(&chip8.display) {
-                println!("Draw error: {}", e);
+        // the window is redrawn when it's resized or uncovered, or while the last frames fade out,
+        // even if the screen didn't change
+        if chip8.take_draw() || frontend.needs_draw() {
+            if let Err(e) = frontend.draw(&chip8) {
+                frontend.error(&format!("Draw error: {}", e));
             }
-            chip8.draw = false;
         }
 
-        if chip8.sound_timer > 0 { 
-            audio.beep();
+        if chip8.sound_timer() > 0 && !rewinding && !paused {
+            frontend.beep(&chip8);
         }
         else {
-            audio.stop_beep();
+            frontend.stop_beep();
+        }
+
+        // wait for the next frame. The deadline advances by exactly one frame, so time lost
+        // oversleeping is made up on the next frames instead of accumulating as drift
+        next_frame += FRAME_DURATION;
+        let now = Instant::now();
+        if next_frame > now {
+            ::std::thread::sleep(next_frame - now);
+        } else if now - next_frame > MAX_FRAME_LAG {
+            next_frame = now;
         }
+    }
 
-        ::std::thread::sleep(Duration::from_millis(2));
+    // the terminal is restored before the last messages
+    drop(frontend);
+    if let (Some(tracer), Some(path)) = (tracer, &options.trace) {
+        if let Err(e) = tracer.finish() {
+            eprintln!("Couldn't write trace {}: {}", path, e);
+        }
+    }
+    if let (Some(recorder), Some(path)) = (recorder, &options.record) {
+        match recorder.finish().save(path) {
+            Ok(()) => println!("Saved movie to {}", path),
+            Err(e) => eprintln!("Couldn't save movie to {}: {}", path, e),
+        }
     }
 }  
\ No newline at end of file
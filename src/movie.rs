@@ -0,0 +1,332 @@
+// Movies: the keys held on every frame of a run, to replay it exactly.
+//
+// The machine is deterministic once the ROM, the random seed, the mode, the quirks and the speed
+// are fixed, so those plus the input are enough to reproduce a run. A checksum of the framebuffer
+// is stored every CHECKSUM_INTERVAL frames to notice when a playback drifts from the recording.
+//
+// Layout (little endian):
+//   magic "C8MV", version (u8)
+//   rom hash (u64), seed (u64), mode (u8), quirks (u16 bitmask), instructions per frame (u32)
+//   frame count (u32), keys of each frame (u16 bitmask)
+//   checksum count (u32), checksums (frame (u32), framebuffer hash (u64))
+//   tap count (u32), taps (frame (u32), keys pressed and released before it ran (u16 bitmask))
+// Version 1 movies have no taps.
+use crate::binary::{Reader, Truncated, Writer};
+use crate::chip8::{Chip8, Mode, NUM_KEYS};
+use crate::quirks::Quirks;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+
+const MAGIC: &[u8; 4] = b"C8MV";
+const VERSION: u8 = 2;
+// once per second
+pub const CHECKSUM_INTERVAL: usize = 60;
+
+#[derive(Debug)]
+pub enum MovieError {
+    // the movie file couldn't be read or written
+    Io(io::Error),
+    // a movie that is corrupted or from an incompatible version
+    Invalid(String),
+    // the movie was recorded with another ROM
+    RomMismatch { expected: u64, actual: u64 },
+    // the framebuffer doesn't match the recording after this frame
+    Desync { frame: usize },
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MovieError::Io(e) => write!(f, "{}", e),
+            MovieError::Invalid(reason) => write!(f, "Invalid movie: {}", reason),
+            MovieError::RomMismatch { expected, actual } => write!(
+                f,
+                "The movie was recorded with another ROM (hash {:016X}, this one is {:016X})",
+                expected, actual
+            ),
+            MovieError::Desync { frame } => write!(f, "Playback desynced at frame {}", frame),
+        }
+    }
+}
+
+impl Error for MovieError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MovieError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MovieError {
+    fn from(e: io::Error) -> MovieError {
+        MovieError::Io(e)
+    }
+}
+
+// FNV-1a, to identify ROMs and framebuffers
+pub fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Movie {
+    pub rom_hash: u64,
+    pub seed: u64,
+    pub mode: Mode,
+    pub quirks: Quirks,
+    // instructions per frame
+    pub ipf: usize,
+    // the keys held on each frame, bit n is key n
+    pub frames: Vec<u16>,
+    // (frame, framebuffer hash after running it)
+    pub checksums: Vec<(usize, u64)>,
+    // (frame, keys tapped before it), the keys pressed and released between two frames
+    pub taps: Vec<(usize, u16)>,
+}
+
+impl Movie {
+    pub fn load(path: &str) -> Result<Movie, MovieError> {
+        Movie::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), MovieError> {
+        Ok(fs::write(path, self.to_bytes())?)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Writer::with_capacity(
+            44 + self.frames.len() * 2 + self.checksums.len() * 12 + self.taps.len() * 6,
+        );
+        out.header(MAGIC, VERSION);
+        out.u64(self.rom_hash);
+        out.u64(self.seed);
+        out.u8(self.mode.id());
+        out.u16(self.quirks.to_bits());
+        out.u32(self.ipf as u32);
+        out.u32(self.frames.len() as u32);
+        for &keys in self.frames.iter() {
+            out.u16(keys);
+        }
+        out.u32(self.checksums.len() as u32);
+        for &(frame, checksum) in self.checksums.iter() {
+            out.u32(frame as u32);
+            out.u64(checksum);
+        }
+        out.u32(self.taps.len() as u32);
+        for &(frame, keys) in self.taps.iter() {
+            out.u32(frame as u32);
+            out.u16(keys);
+        }
+        out.into_bytes()
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Movie, MovieError> {
+        let mut input = Reader::new(data);
+        let version = input.header(MAGIC)?.ok_or_else(|| invalid("not a movie"))?;
+        if version == 0 || version > VERSION {
+            return Err(invalid(&format!("unsupported version {}", version)));
+        }
+
+        let rom_hash = input.u64()?;
+        let seed = input.u64()?;
+        let id = input.u8()?;
+        let mode = Mode::from_id(id).ok_or_else(|| invalid(&format!("unknown mode {}", id)))?;
+        let quirks = Quirks::from_bits(input.u16()?);
+        let ipf = input.u32()? as usize;
+        if ipf == 0 {
+            return Err(invalid("no instructions per frame"));
+        }
+
+        let frame_count = input.u32()? as usize;
+        let mut frames = Vec::with_capacity(frame_count.min(input.remaining() / 2));
+        for _ in 0..frame_count {
+            frames.push(input.u16()?);
+        }
+        let checksum_count = input.u32()? as usize;
+        let mut checksums = Vec::with_capacity(checksum_count.min(input.remaining() / 12));
+        for _ in 0..checksum_count {
+            let frame = input.u32()? as usize;
+            if frame >= frame_count {
+                return Err(invalid(&format!("checksum for missing frame {}", frame)));
+            }
+            checksums.push((frame, input.u64()?));
+        }
+        let mut taps = Vec::new();
+        if version >= 2 {
+            let tap_count = input.u32()? as usize;
+            taps.reserve(tap_count.min(input.remaining() / 6));
+            for _ in 0..tap_count {
+                let frame = input.u32()? as usize;
+                if frame >= frame_count {
+                    return Err(invalid(&format!("taps for missing frame {}", frame)));
+                }
+                taps.push((frame, input.u16()?));
+            }
+        }
+        if !input.is_empty() {
+            return Err(invalid("unexpected data at the end"));
+        }
+
+        Ok(Movie {
+            rom_hash,
+            seed,
+            mode,
+            quirks,
+            ipf,
+            frames,
+            checksums,
+            taps,
+        })
+    }
+}
+
+// Records a movie while the machine runs
+pub struct Recorder {
+    movie: Movie,
+}
+
+impl Recorder {
+    // chip8 must have been created with the seed given, and not have run yet
+    pub fn new(chip8: &Chip8, rom: &[u8], seed: u64, ipf: usize) -> Recorder {
+        Recorder {
+            movie: Movie {
+                rom_hash: hash(rom),
+                seed,
+                mode: chip8.mode(),
+                quirks: chip8.quirks(),
+                ipf,
+                frames: Vec::new(),
+                checksums: Vec::new(),
+                taps: Vec::new(),
+            },
+        }
+    }
+
+    // call before each frame runs, once its input is in: the keys held during it, and the ones
+    // tapped before it, are recorded
+    pub fn record_input(&mut self, chip8: &Chip8) {
+        let frame = self.movie.frames.len();
+        let held = key_mask(chip8);
+        self.movie.frames.push(held);
+        let (pressed, _) = chip8.key_edges();
+        if pressed & !held != 0 {
+            self.movie.taps.push((frame, pressed & !held));
+        }
+    }
+
+    // call after each frame run
+    pub fn record_frame(&mut self, chip8: &Chip8) {
+        let frame = self.movie.frames.len() - 1;
+        if (frame + 1).is_multiple_of(CHECKSUM_INTERVAL) {
+            let checksum = hash(chip8.framebuffer().pixels());
+            self.movie.checksums.push((frame, checksum));
+        }
+    }
+
+    pub fn finish(self) -> Movie {
+        self.movie
+    }
+}
+
+// Replays a movie, overriding the keyboard
+pub struct Player {
+    movie: Movie,
+    // the frame being played
+    frame: usize,
+    // the next entry of movie.checksums to check
+    next_checksum: usize,
+    // the next entry of movie.taps to apply
+    next_tap: usize,
+}
+
+impl Player {
+    // fails when rom isn't the ROM the movie was recorded with
+    pub fn new(movie: Movie, rom: &[u8]) -> Result<Player, MovieError> {
+        let actual = hash(rom);
+        if actual != movie.rom_hash {
+            return Err(MovieError::RomMismatch {
+                expected: movie.rom_hash,
+                actual,
+            });
+        }
+        Ok(Player {
+            movie,
+            frame: 0,
+            next_checksum: 0,
+            next_tap: 0,
+        })
+    }
+
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    pub fn finished(&self) -> bool {
+        self.frame >= self.movie.frames.len()
+    }
+
+    // call before running a frame: sets the keys recorded for it.
+    // Returns false once the movie is over
+    pub fn apply_input(&mut self, chip8: &mut Chip8) -> bool {
+        let keys = match self.movie.frames.get(self.frame) {
+            Some(&keys) => keys,
+            None => return false,
+        };
+        let taps = match self.movie.taps.get(self.next_tap) {
+            Some(&(frame, taps)) if frame == self.frame => {
+                self.next_tap += 1;
+                taps
+            }
+            _ => 0,
+        };
+        for key in 0..NUM_KEYS {
+            if taps & (1 << key) != 0 {
+                chip8.key_down(key);
+                chip8.key_up(key);
+            }
+            chip8.set_key(key, keys & (1 << key) != 0);
+        }
+        true
+    }
+
+    // call after running a frame: compares the framebuffer with the recording and moves to the
+    // next frame
+    pub fn verify(&mut self, chip8: &Chip8) -> Result<(), MovieError> {
+        let frame = self.frame;
+        self.frame += 1;
+        match self.movie.checksums.get(self.next_checksum) {
+            Some(&(checksum_frame, checksum)) if checksum_frame == frame => {
+                self.next_checksum += 1;
+                if hash(chip8.framebuffer().pixels()) != checksum {
+                    return Err(MovieError::Desync { frame });
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn key_mask(chip8: &Chip8) -> u16 {
+    let keys = chip8.keys();
+    (0..NUM_KEYS).fold(0, |mask, key| mask | (keys[key] as u16) << key)
+}
+
+fn invalid(reason: &str) -> MovieError {
+    MovieError::Invalid(reason.to_string())
+}
+
+impl From<Truncated> for MovieError {
+    fn from(_: Truncated) -> MovieError {
+        invalid("truncated")
+    }
+}
+
+#[cfg(test)]
+#[path = "./movie_tests.rs"]
+mod movie_tests;
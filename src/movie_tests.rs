@@ -0,0 +1,132 @@
+use super::*;
+
+// draws a random digit while key 0 is held
+const ROM: [u8; 12] = [
+    0x60, 0x00, // v0 = 0
+    0xE0, 0xA1, // skip the next instruction if key v0 isn't pressed
+    0xC2, 0xFF, // v2 = random
+    0xF2, 0x29, // I = font digit v2
+    0xD1, 0x15, // draw it at (v1, v1)
+    0x12, 0x00, // loop
+];
+
+fn machine(seed: u64) -> Chip8 {
+    let mut instance = Chip8::new_with_seed(Quirks::default(), seed);
+    instance.load_rom_bytes(&ROM).unwrap();
+    instance
+}
+
+// 120 frames, key 0 held from frame 30 to 89
+fn record(seed: u64) -> (Movie, Chip8) {
+    let mut instance = machine(seed);
+    let mut recorder = Recorder::new(&instance, &ROM, seed, 10);
+    for frame in 0..120 {
+        instance.set_key(0, (30..90).contains(&frame));
+        recorder.record_input(&instance);
+        instance.run_frame(10).unwrap();
+        recorder.record_frame(&instance);
+    }
+    (recorder.finish(), instance)
+}
+
+#[test]
+fn movie_round_trip() {
+    let (movie, _) = record(7);
+    assert_eq!(movie.frames.len(), 120);
+    assert_eq!(movie.checksums.len(), 2);
+    assert_eq!(movie.frames[29], 0);
+    assert_eq!(movie.frames[30], 1);
+    assert_eq!(Movie::from_bytes(&movie.to_bytes()).unwrap(), movie);
+}
+
+#[test]
+fn playback_reproduces_recording() {
+    let (movie, recorded) = record(7);
+    let mut instance = machine(movie.seed);
+    let mut player = Player::new(movie, &ROM).unwrap();
+    while player.apply_input(&mut instance) {
+        instance.run_frame(player.movie().ipf).unwrap();
+        player.verify(&instance).unwrap();
+    }
+    assert!(player.finished());
+    assert_eq!(instance.save_state(), recorded.save_state());
+}
+
+#[test]
+fn playback_detects_desync() {
+    let (movie, _) = record(7);
+    // another seed draws other digits once the key is held
+    let mut instance = machine(8);
+    let mut player = Player::new(movie, &ROM).unwrap();
+    let mut result = Ok(());
+    while result.is_ok() && player.apply_input(&mut instance) {
+        instance.run_frame(10).unwrap();
+        result = player.verify(&instance);
+    }
+    match result {
+        Err(MovieError::Desync { frame }) => assert_eq!(frame, 59),
+        other => panic!("expected a desync, got {:?}", other),
+    }
+}
+
+#[test]
+fn taps_are_replayed() {
+    let mut instance = machine(7);
+    let mut recorder = Recorder::new(&instance, &ROM, 7, 10);
+    for frame in 0..120 {
+        if frame % 20 == 10 {
+            instance.key_down(0);
+            instance.key_up(0);
+        }
+        recorder.record_input(&instance);
+        instance.run_frame(10).unwrap();
+        recorder.record_frame(&instance);
+    }
+    let movie = recorder.finish();
+    assert_eq!(
+        movie.taps,
+        [(10, 1), (30, 1), (50, 1), (70, 1), (90, 1), (110, 1)]
+    );
+    assert_eq!(Movie::from_bytes(&movie.to_bytes()).unwrap(), movie);
+
+    let mut replayed = machine(7);
+    let mut player = Player::new(movie, &ROM).unwrap();
+    while player.apply_input(&mut replayed) {
+        replayed.run_frame(10).unwrap();
+        player.verify(&replayed).unwrap();
+    }
+    assert_eq!(replayed.save_state(), instance.save_state());
+}
+
+#[test]
+fn version_1_has_no_taps() {
+    let (movie, _) = record(7);
+    let mut bytes = movie.to_bytes();
+    bytes[4] = 1;
+    // without the tap count
+    bytes.truncate(bytes.len() - 4);
+    assert_eq!(Movie::from_bytes(&bytes).unwrap(), movie);
+}
+
+#[test]
+fn player_rejects_other_rom() {
+    let (movie, _) = record(7);
+    let mut other = ROM;
+    other[1] = 0x01;
+    match Player::new(movie, &other) {
+        Err(MovieError::RomMismatch { .. }) => {}
+        _ => panic!("expected a ROM mismatch"),
+    }
+}
+
+#[test]
+fn from_bytes_rejects_bad_data() {
+    let (movie, _) = record(7);
+    let bytes = movie.to_bytes();
+    for data in [&bytes[..bytes.len() - 1], &bytes[1..]].iter() {
+        match Movie::from_bytes(data) {
+            Err(MovieError::Invalid(_)) => {}
+            _ => panic!("expected an invalid movie"),
+        }
+    }
+}
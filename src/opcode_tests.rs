@@ -1,22 +1,25 @@
-use super::Chip8;
-use assert_hex;
+use super::{Access, Chip8, MemoryObserver, Mode};
+use crate::error::Chip8Error;
+use crate::quirks::Quirks;
+use std::cell::RefCell;
+use std::rc::Rc;
 
 #[test]
 fn op_1nnn() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x15;
     instance.memory[0x201] = 0x1F;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
     assert_hex::assert_eq_hex!(instance.pc, 0x051F);
 }
 
 #[test]
 fn op_2nnn() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x21;
     instance.memory[0x201] = 0x3F;
 
-    instance.cycle().unwrap();
+    instance.step().unwrap();
     assert_hex::assert_eq_hex!(instance.pc, 0x013F);
     assert_hex::assert_eq_hex!(instance.stack[0], 0x202);
     assert_hex::assert_eq_hex!(instance.sp, 0x1);
@@ -24,275 +27,275 @@ fn op_2nnn() {
 
 #[test]
 fn op_00ee() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x21;
     instance.memory[0x201] = 0x3F;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x013F] = 0x00;
     instance.memory[0x0140] = 0xEE;
 
-    instance.cycle().unwrap();
+    instance.step().unwrap();
     assert_hex::assert_eq_hex!(instance.pc, 0x0202);
     assert_hex::assert_eq_hex!(instance.sp, 0x0);
 }
 
 #[test]
 fn op_3xkk() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.v[2] = 0xA;
     instance.memory[0x200] = 0x32;
     instance.memory[0x201] = 0x0A;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.pc, 0x0204);
 }
 
 #[test]
 fn op_4xkk() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.v[2] = 0xB;
     instance.memory[0x200] = 0x42;
     instance.memory[0x201] = 0x0A;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.pc, 0x0204);
 }
 
 #[test]
 fn op_5xy0() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.v[2] = 0xB;
     instance.v[1] = 0xB;
     instance.memory[0x200] = 0x52;
     instance.memory[0x201] = 0x10;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.pc, 0x0204);
 }
 
 #[test]
 fn op_6xkk() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x6A;
     instance.memory[0x201] = 0xCA;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.v[0xA], 0x00CA);
 }
 
 #[test]
 fn op_7xkk() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x6A;
     instance.memory[0x201] = 0xCA;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x202] = 0x7A;
     instance.memory[0x203] = 0x01;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.v[0xA], 0xCB);
 }
 
 #[test]
 fn op_8xy0() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x6A;
     instance.memory[0x201] = 0xCA;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x202] = 0x8A;
     instance.memory[0x203] = 0xE0;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.v[0xA], instance.v[0xE]);
 }
 
 #[test]
 fn op_8xy1() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x6A;
     instance.memory[0x201] = 0xCA;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x202] = 0x6B;
     instance.memory[0x203] = 0xCA;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x204] = 0x8A;
     instance.memory[0x205] = 0xB1;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.v[0xA], 0xCA);
 }
 
 #[test]
 fn op_8xy2() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x6A;
     instance.memory[0x201] = 0xCA;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x202] = 0x6B;
     instance.memory[0x203] = 0xCA;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x204] = 0x8A;
     instance.memory[0x205] = 0xB2;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.v[0xA], 0xCA);
 }
 
 #[test]
 fn op_8xy3() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x6A;
     instance.memory[0x201] = 0xCA;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x202] = 0x6B;
     instance.memory[0x203] = 0xCA;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x204] = 0x8A;
     instance.memory[0x205] = 0xB3;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.v[0xA], 0x0);
 }
 
 #[test]
 fn op_8xy4() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x6A;
     instance.memory[0x201] = 0x5;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x202] = 0x6B;
     instance.memory[0x203] = 0x02;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x204] = 0x8A;
     instance.memory[0x205] = 0xB4;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
     assert_hex::assert_eq_hex!(instance.v[0xA], 0x07);
 }
 
 #[test]
 fn op_8xy4_overflow() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x6A;
     instance.memory[0x201] = 0xFF;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x202] = 0x6B;
     instance.memory[0x203] = 0xCA;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x204] = 0x8A;
     instance.memory[0x205] = 0xB4;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
     assert_hex::assert_eq_hex!(instance.v[0xF], 0x1);
     assert_hex::assert_eq_hex!(instance.v[0xA], 0xC9);
 }
 
 #[test]
 fn op_8xy5() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x6A;
     instance.memory[0x201] = 0xFF;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x202] = 0x6B;
     instance.memory[0x203] = 0xCA;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x204] = 0x8A;
     instance.memory[0x205] = 0xB5;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
     assert_hex::assert_eq_hex!(instance.v[0xF], 0x1);
     assert_hex::assert_eq_hex!(instance.v[0xA], 0x35);
 }
 
 #[test]
 fn op_8xy6() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x6A;
     instance.memory[0x201] = 0x30;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x202] = 0x6B;
     instance.memory[0x203] = 0xCA;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x204] = 0x8A;
     instance.memory[0x205] = 0xB6;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
     assert_hex::assert_eq_hex!(instance.v[0xF], 0x0);
     assert_hex::assert_eq_hex!(instance.v[0xA], 0x18);
 }
 
 #[test]
 fn op_8xy7() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x6A;
     instance.memory[0x201] = 0xCA;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x202] = 0x6B;
     instance.memory[0x203] = 0xFA;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x204] = 0x8A;
     instance.memory[0x205] = 0xB7;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
     assert_hex::assert_eq_hex!(instance.v[0xF], 0x1);
     assert_hex::assert_eq_hex!(instance.v[0xA], 0x30);
 }
 
 #[test]
 fn op_8xye() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x6A;
     instance.memory[0x201] = 0xFF;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x202] = 0x6B;
     instance.memory[0x203] = 0xCA;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x204] = 0x8A;
     instance.memory[0x205] = 0xBE;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
     assert_hex::assert_eq_hex!(instance.v[0xF], 0x1);
     assert_hex::assert_eq_hex!(instance.v[0xA], 0xFE);
 }
 
 #[test]
 fn op_9xy0() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x6A;
     instance.memory[0x201] = 0xFF;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x202] = 0x6B;
     instance.memory[0x203] = 0xCA;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x204] = 0x9A;
     instance.memory[0x205] = 0xB0;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
     assert_hex::assert_eq_hex!(instance.pc, 0x208);
 }
 
 #[test]
 fn op_annn() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0xAA;
     instance.memory[0x201] = 0xFF;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.i, 0xAFF);
     assert_hex::assert_eq_hex!(instance.pc, 0x202);
@@ -300,104 +303,239 @@ fn op_annn() {
 
 #[test]
 fn op_bnnn() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.memory[0x200] = 0x60;
     instance.memory[0x201] = 0x3A;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     instance.memory[0x202] = 0xBA;
     instance.memory[0x203] = 0x37;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.pc, 0x3A + 0xA37);
 }
 
 #[test]
 fn op_ex9e() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.keyboard[0] = true;
     instance.memory[0x200] = 0xE0;
     instance.memory[0x201] = 0x9E;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.pc, 0x204);
 }
 
-// #[test]
-// fn op_exa1() {
-//     let mut instance = Chip8::new();
-//     instance.keyboard[0] = true;
-//     instance.memory[0x200] = 0xE0;
-//     instance.memory[0x201] = 0x9E;
-//     instance.cycle().unwrap();
+#[test]
+fn op_exa1() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.keyboard[0] = true;
+    instance.memory[0x200] = 0xE0;
+    instance.memory[0x201] = 0xA1;
+    instance.step().unwrap();
+
+    assert_hex::assert_eq_hex!(instance.pc, 0x202);
+}
+
+#[test]
+fn op_ex9e_reads_the_key_in_vx() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.v[0x3] = 0x5;
+    instance.memory[0x200] = 0xE3;
+    instance.memory[0x201] = 0x9E;
+    // key 3 is the register, not the key
+    instance.key_down(0x3);
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x202);
+
+    instance.pc = 0x200;
+    instance.key_down(0x5);
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x204);
+}
+
+#[test]
+fn key_tap_lasts_until_the_end_of_the_frame() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.memory[0x200] = 0xE0;
+    instance.memory[0x201] = 0x9E;
+    instance.key_down(0x0);
+    instance.key_up(0x0);
+    assert_eq!(instance.key_edges(), (0x1, 0x1));
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x204);
+
+    instance.handle_timers();
+    assert_eq!(instance.key_edges(), (0, 0));
+    instance.pc = 0x200;
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x202);
+}
 
-//     assert_hex::assert_eq_hex!(instance.pc, 0x202);
-// }
+#[test]
+fn key_edges_only_on_changes() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.key_down(0x2);
+    instance.handle_timers();
+    // still held: no new press
+    instance.key_down(0x2);
+    instance.key_up(0x7);
+    assert_eq!(instance.key_edges(), (0, 0));
+    assert!(instance.keys()[0x2]);
+    instance.key_up(0x2);
+    assert_eq!(instance.key_edges(), (0, 0x4));
+}
 
 #[test]
 fn op_fx07() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.delay_timer = 0x55;
     instance.memory[0x200] = 0xF1;
     instance.memory[0x201] = 0x07;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.v[0x1], 0x55);
 }
 
+// fx0a at 0x200, waiting for a key to store in v1
+fn waiting_for_key(quirks: Quirks) -> Chip8 {
+    let mut instance = Chip8::new(quirks);
+    instance.memory[0x200] = 0xF1;
+    instance.memory[0x201] = 0x0A;
+    instance
+}
+
+#[test]
+fn op_fx0a_waits_for_release() {
+    let mut instance = waiting_for_key(Quirks::default());
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x200);
+
+    // keys 3 and 7 pressed together, only 3 is stored once released
+    instance.keyboard[0x7] = true;
+    instance.keyboard[0x3] = true;
+    instance.step().unwrap();
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x200);
+    instance.keyboard[0x3] = false;
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x202);
+    assert_hex::assert_eq_hex!(instance.v[0x1], 0x3);
+    assert_eq!(instance.key_wait, None);
+}
+
+#[test]
+fn op_fx0a_takes_a_tap_once() {
+    let mut instance = waiting_for_key(Quirks::default());
+    // pressed and released between two frames
+    instance.key_down(0x4);
+    instance.key_up(0x4);
+    instance.step().unwrap();
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x202);
+    assert_hex::assert_eq_hex!(instance.v[0x1], 0x4);
+
+    // the same tap doesn't satisfy the next fx0a
+    instance.pc = 0x200;
+    instance.step().unwrap();
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x200);
+}
+
+#[test]
+fn op_fx0a_timers_run_while_waiting() {
+    let mut instance = waiting_for_key(Quirks::default());
+    instance.delay_timer = 5;
+    instance.run_frame(10).unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x200);
+    assert_eq!(instance.delay_timer, 4);
+}
+
+#[test]
+fn quirk_key_wait_on_press() {
+    let mut instance = waiting_for_key(Quirks {
+        key_wait_on_release: false,
+        ..Default::default()
+    });
+    instance.keyboard[0x9] = true;
+    instance.keyboard[0xC] = true;
+    instance.step().unwrap();
+    // a single key is stored, and a single instruction skipped
+    assert_hex::assert_eq_hex!(instance.pc, 0x202);
+    assert_hex::assert_eq_hex!(instance.v[0x1], 0x9);
+}
+
+#[test]
+fn quirk_key_wait_beep() {
+    let mut instance = waiting_for_key(Quirks::cosmac_vip());
+    instance.step().unwrap();
+    assert_eq!(instance.sound_timer, 0);
+    instance.keyboard[0x2] = true;
+    instance.run_frame(10).unwrap();
+    assert!(instance.sound_timer > 0);
+    instance.keyboard[0x2] = false;
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.v[0x1], 0x2);
+
+    let mut instance = waiting_for_key(Quirks::default());
+    instance.keyboard[0x2] = true;
+    instance.step().unwrap();
+    assert_eq!(instance.sound_timer, 0);
+}
+
 #[test]
 fn op_fx15() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.v[0x7] = 0x55;
     instance.memory[0x200] = 0xF7;
     instance.memory[0x201] = 0x15;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.v[0x7], 0x55);
 }
 
 #[test]
 fn op_fx18() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.v[0x7] = 0x55;
     instance.memory[0x200] = 0xF7;
     instance.memory[0x201] = 0x18;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.v[0x7], 0x55);
 }
 
 #[test]
 fn op_fx1e() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.v[0x7] = 0x55;
     instance.i = 0x1;
     instance.memory[0x200] = 0xF7;
     instance.memory[0x201] = 0x1E;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.i, 0x56);
 }
 
 #[test]
 fn op_fx29() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.v[0x7] = 0x1;
     instance.memory[0x200] = 0xF7;
     instance.memory[0x201] = 0x29;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.i, 0x5);
 }
 
 #[test]
 fn op_fx33() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.v[0x7] = 0x91;
     instance.i = 0x300;
     instance.memory[0x200] = 0xF7;
     instance.memory[0x201] = 0x33;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.memory[instance.i as usize], 0x1);
     assert_hex::assert_eq_hex!(instance.memory[(instance.i + 1) as usize], 0x4);
@@ -406,24 +544,805 @@ fn op_fx33() {
 
 #[test]
 fn op_fx55() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.v[0x1] = 0x91;
     instance.i = 0x300;
     instance.memory[0x200] = 0xF1;
     instance.memory[0x201] = 0x55;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.memory[(instance.i + 1) as usize], 0x91);
 }
 
 #[test]
 fn op_fx65() {
-    let mut instance = Chip8::new();
+    let mut instance = Chip8::new(Quirks::default());
     instance.i = 0x300;
     instance.memory[instance.i as usize] = 0x35;
     instance.memory[0x200] = 0xF0;
     instance.memory[0x201] = 0x65;
-    instance.cycle().unwrap();
+    instance.step().unwrap();
 
     assert_hex::assert_eq_hex!(instance.v[0x0], 0x35);
-}
\ No newline at end of file
+}
+#[test]
+fn quirk_shift_uses_vy() {
+    let quirks = Quirks {
+        shift_uses_vy: true,
+        ..Default::default()
+    };
+    let mut instance = Chip8::new(quirks);
+    instance.v[0xA] = 0xFF;
+    instance.v[0xB] = 0x31;
+    instance.memory[0x200] = 0x8A;
+    instance.memory[0x201] = 0xB6;
+    instance.memory[0x202] = 0x8C;
+    instance.memory[0x203] = 0xBE;
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.v[0xA], 0x18);
+    assert_hex::assert_eq_hex!(instance.v[0xF], 0x1);
+
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.v[0xC], 0x62);
+    assert_hex::assert_eq_hex!(instance.v[0xF], 0x0);
+}
+
+#[test]
+fn quirk_shift_ignores_vy() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.v[0xA] = 0xFF;
+    instance.v[0xB] = 0x31;
+    instance.memory[0x200] = 0x8A;
+    instance.memory[0x201] = 0xB6;
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.v[0xA], 0x7F);
+    assert_hex::assert_eq_hex!(instance.v[0xF], 0x1);
+}
+
+#[test]
+fn quirk_load_store_increments_i() {
+    let quirks = Quirks {
+        load_store_increments_i: true,
+        ..Default::default()
+    };
+    let mut instance = Chip8::new(quirks);
+    instance.v[0x0] = 0x12;
+    instance.v[0x1] = 0x34;
+    instance.v[0x2] = 0x56;
+    instance.i = 0x300;
+    instance.memory[0x200] = 0xF2;
+    instance.memory[0x201] = 0x55;
+    instance.memory[0x202] = 0xF1;
+    instance.memory[0x203] = 0x65;
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.memory[0x302], 0x56);
+    assert_hex::assert_eq_hex!(instance.i, 0x303);
+
+    instance.i = 0x301;
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.v[0x0], 0x34);
+    assert_hex::assert_eq_hex!(instance.v[0x1], 0x56);
+    assert_hex::assert_eq_hex!(instance.i, 0x303);
+}
+
+#[test]
+fn quirk_load_store_keeps_i() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.i = 0x300;
+    instance.memory[0x200] = 0xF2;
+    instance.memory[0x201] = 0x55;
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.i, 0x300);
+}
+
+#[test]
+fn quirk_jump_uses_vx() {
+    let quirks = Quirks {
+        jump_uses_vx: true,
+        ..Default::default()
+    };
+    let mut instance = Chip8::new(quirks);
+    instance.v[0x0] = 0x10;
+    instance.v[0xA] = 0x3A;
+    instance.memory[0x200] = 0xBA;
+    instance.memory[0x201] = 0x37;
+    instance.step().unwrap();
+
+    assert_hex::assert_eq_hex!(instance.pc, 0xA37 + 0x3A);
+}
+
+#[test]
+fn quirk_vf_reset() {
+    let quirks = Quirks {
+        vf_reset: true,
+        ..Default::default()
+    };
+    let mut instance = Chip8::new(quirks);
+    for (address, opcode) in [0x8A, 0xB1, 0x8A, 0xB2, 0x8A, 0xB3].chunks(2).enumerate() {
+        instance.memory[0x200 + address * 2] = opcode[0];
+        instance.memory[0x201 + address * 2] = opcode[1];
+    }
+    for _ in 0..3 {
+        instance.v[0xF] = 0x1;
+        instance.step().unwrap();
+        assert_hex::assert_eq_hex!(instance.v[0xF], 0x0);
+    }
+}
+
+#[test]
+fn quirk_vf_untouched() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.v[0xF] = 0x1;
+    instance.memory[0x200] = 0x8A;
+    instance.memory[0x201] = 0xB1;
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.v[0xF], 0x1);
+}
+
+// draws a 1 pixel high, 8 pixel wide line with the top left corner at (vx, vy)
+fn draw_line_at(quirks: Quirks, x: u8, y: u8) -> Chip8 {
+    let mut instance = Chip8::new(quirks);
+    instance.v[0x0] = x;
+    instance.v[0x1] = y;
+    instance.i = 0x300;
+    instance.memory[0x300] = 0xFF;
+    instance.memory[0x200] = 0xD0;
+    instance.memory[0x201] = 0x11;
+    instance.step().unwrap();
+    instance
+}
+
+#[test]
+fn quirk_clip_sprites() {
+    let instance = draw_line_at(Quirks::default(), 60, 31);
+    assert_eq!(instance.display.row(31)[60..64], [1, 1, 1, 1]);
+    assert_eq!(instance.display.row(31)[0..4], [0, 0, 0, 0]);
+}
+
+#[test]
+fn quirk_wrap_sprites() {
+    let quirks = Quirks {
+        clip_sprites: false,
+        ..Default::default()
+    };
+    let instance = draw_line_at(quirks, 60, 31);
+    assert_eq!(instance.display.row(31)[60..64], [1, 1, 1, 1]);
+    assert_eq!(instance.display.row(31)[0..4], [1, 1, 1, 1]);
+}
+
+#[test]
+fn quirk_sprite_start_wraps() {
+    // the starting position always wraps, regardless of the clipping quirk
+    let instance = draw_line_at(Quirks::default(), 64 + 2, 32 + 5);
+    assert_eq!(instance.display.row(5)[2..10], [1; 8]);
+}
+
+#[test]
+fn sprite_start_at_largest_coordinates() {
+    // (255, 255) wraps to (63, 31), the rest of the line is clipped
+    let instance = draw_line_at(Quirks::default(), 255, 255);
+    assert_eq!(instance.display.get(63, 31), 1);
+    assert_eq!(
+        instance
+            .display
+            .pixels()
+            .iter()
+            .filter(|&&pixel| pixel != 0)
+            .count(),
+        1
+    );
+}
+
+// draws a 3 row high, 1 pixel wide sprite at (vx, vy)
+fn draw_column_at(quirks: Quirks, x: u8, y: u8) -> Chip8 {
+    let mut instance = Chip8::new(quirks);
+    instance.v[0x0] = x;
+    instance.v[0x1] = y;
+    instance.i = 0x300;
+    instance.memory[0x300..0x303].copy_from_slice(&[0x80, 0x80, 0x80]);
+    instance.memory[0x200] = 0xD0;
+    instance.memory[0x201] = 0x13;
+    instance.step().unwrap();
+    instance
+}
+
+#[test]
+fn quirk_clip_sprites_bottom() {
+    let instance = draw_column_at(Quirks::default(), 5, 30);
+    assert_eq!(
+        (instance.display.get(5, 30), instance.display.get(5, 31)),
+        (1, 1)
+    );
+    assert_eq!(instance.display.get(5, 0), 0);
+}
+
+#[test]
+fn quirk_wrap_sprites_bottom() {
+    let quirks = Quirks {
+        clip_sprites: false,
+        ..Default::default()
+    };
+    let instance = draw_column_at(quirks, 5, 30);
+    assert_eq!(
+        (instance.display.get(5, 30), instance.display.get(5, 31)),
+        (1, 1)
+    );
+    assert_eq!(instance.display.get(5, 0), 1);
+}
+
+#[test]
+fn sprite_position_in_vf() {
+    // vf is read for the position before it's set by the collision
+    let mut instance = Chip8::new(Quirks::default());
+    instance.v[0xF] = 10;
+    instance.i = 0x300;
+    instance.memory[0x300] = 0x80;
+    instance.memory[0x200] = 0xDF;
+    instance.memory[0x201] = 0xF1;
+    instance.step().unwrap();
+    assert_eq!(instance.display.get(10, 10), 1);
+    assert_hex::assert_eq_hex!(instance.v[0xF], 0x0);
+}
+
+#[test]
+fn quirk_collision_counts_rows() {
+    let mut instance = superchip();
+    instance.i = 0x300;
+    instance.memory[0x300..0x304].copy_from_slice(&[0xFF, 0x00, 0xFF, 0xFF]);
+    let program = [
+        0x00, 0xFF, // hires
+        0xD0, 0x14, // draw at (0, 0)
+        0xD0, 0x14, // draw it again, erasing it: 3 rows collide
+        0x61, 0x3E, // v1 = 62
+        0xD0, 0x14, // draw at (0, 62): 2 rows clipped
+    ];
+    instance.memory[0x200..0x20A].copy_from_slice(&program);
+    instance.step().unwrap();
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.v[0xF], 0x0);
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.v[0xF], 0x3);
+    instance.step().unwrap();
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.v[0xF], 0x2);
+}
+
+#[test]
+fn quirk_collision_counts_rows_only_in_hires() {
+    let mut instance = superchip();
+    instance.i = 0x300;
+    instance.memory[0x300..0x302].copy_from_slice(&[0xFF, 0xFF]);
+    instance.memory[0x200..0x204].copy_from_slice(&[0xD0, 0x12, 0xD0, 0x12]);
+    instance.step().unwrap();
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.v[0xF], 0x1);
+}
+
+#[test]
+fn quirk_collision_counts_rows_once_with_both_planes() {
+    let mut instance = xochip();
+    instance.quirks.collision_counts_rows = true;
+    instance.quirks.clip_sprites = true;
+    instance.planes = 0x3;
+    instance.i = 0x300;
+    // plane 1, then plane 2
+    instance.memory[0x300..0x308]
+        .copy_from_slice(&[0xFF, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0xFF]);
+    let program = [
+        0x00, 0xFF, // hires
+        0xD0, 0x14, // draw at (0, 0)
+        0xD0, 0x14, // draw it again: 4 rows collide, on one plane or both
+        0x61, 0x3E, // v1 = 62
+        0xD0, 0x14, // draw at (0, 62): 2 rows clipped
+    ];
+    instance.memory[0x200..0x20A].copy_from_slice(&program);
+    instance.step().unwrap();
+    instance.step().unwrap();
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.v[0xF], 0x4);
+    instance.step().unwrap();
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.v[0xF], 0x2);
+}
+
+#[test]
+fn quirk_display_wait() {
+    let quirks = Quirks {
+        display_wait: true,
+        ..Default::default()
+    };
+    let mut instance = Chip8::new(quirks);
+    instance.i = 0x300;
+    instance.memory[0x300] = 0x80;
+    // nothing is drawn before the vertical blank
+    instance.op_dxyn(0, 0, 1).unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x200);
+    assert_eq!(instance.display.get(0, 0), 0);
+
+    instance.handle_timers();
+    instance.op_dxyn(0, 0, 1).unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x202);
+    assert_eq!(instance.display.get(0, 0), 1);
+
+    // the next sprite has to wait for the next vertical blank
+    instance.op_dxyn(0, 0, 1).unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x202);
+    assert_eq!(instance.display.get(0, 0), 1);
+}
+
+fn superchip() -> Chip8 {
+    let mut instance = Chip8::new(Quirks::superchip());
+    instance.set_mode(Mode::SuperChip);
+    instance
+}
+
+#[test]
+fn schip_opcodes_unknown_in_chip8_mode() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.memory[0x200] = 0x00;
+    instance.memory[0x201] = 0xFF;
+    assert!(instance.step().is_err());
+}
+
+#[test]
+fn op_00ff_00fe() {
+    let mut instance = superchip();
+    instance.memory[0x200] = 0x00;
+    instance.memory[0x201] = 0xFF;
+    instance.memory[0x202] = 0x00;
+    instance.memory[0x203] = 0xFE;
+    instance.step().unwrap();
+    assert_eq!(instance.display.width(), 128);
+    assert_eq!(instance.display.height(), 64);
+
+    instance.step().unwrap();
+    assert_eq!(instance.display.width(), 64);
+    assert_eq!(instance.display.height(), 32);
+    assert_hex::assert_eq_hex!(instance.pc, 0x204);
+}
+
+#[test]
+fn op_00cn() {
+    let mut instance = superchip();
+    instance.display.set(3, 0, 1);
+    instance.memory[0x200] = 0x00;
+    instance.memory[0x201] = 0xC5;
+    instance.step().unwrap();
+    assert_eq!(instance.display.get(3, 0), 0);
+    assert_eq!(instance.display.get(3, 5), 1);
+}
+
+#[test]
+fn op_00fb_00fc() {
+    let mut instance = superchip();
+    instance.display.set(0, 2, 1);
+    instance.memory[0x200] = 0x00;
+    instance.memory[0x201] = 0xFB;
+    instance.memory[0x202] = 0x00;
+    instance.memory[0x203] = 0xFC;
+    instance.step().unwrap();
+    assert_eq!(instance.display.row(2)[0..5], [0, 0, 0, 0, 1]);
+
+    instance.step().unwrap();
+    assert_eq!(instance.display.row(2)[0..5], [1, 0, 0, 0, 0]);
+}
+
+#[test]
+fn op_00fd() {
+    let mut instance = superchip();
+    instance.memory[0x200] = 0x00;
+    instance.memory[0x201] = 0xFD;
+    instance.step().unwrap();
+    assert!(instance.exited);
+
+    // nothing runs after exiting
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x200);
+}
+
+#[test]
+fn op_dxy0() {
+    let mut instance = superchip();
+    instance.i = 0x300;
+    for byte in 0..32 {
+        instance.memory[0x300 + byte] = 0xFF;
+    }
+    instance.memory[0x200] = 0xD0;
+    instance.memory[0x201] = 0x00;
+    instance.step().unwrap();
+
+    assert_eq!(
+        instance.display.row(0)[0..17],
+        [[1; 16].as_ref(), &[0]].concat()[..]
+    );
+    assert_eq!(instance.display.row(15)[0..16], [1; 16]);
+    assert_eq!(instance.display.row(16)[0..16], [0; 16]);
+    assert_hex::assert_eq_hex!(instance.v[0xF], 0x0);
+}
+
+#[test]
+fn op_fx30() {
+    let mut instance = superchip();
+    instance.v[0x3] = 0x2;
+    instance.memory[0x200] = 0xF3;
+    instance.memory[0x201] = 0x30;
+    instance.step().unwrap();
+
+    assert_hex::assert_eq_hex!(instance.i, 0x50 + 20);
+    assert_hex::assert_eq_hex!(instance.memory[instance.i as usize], 0x3E);
+}
+
+#[test]
+fn op_fx75_fx85() {
+    let mut instance = superchip();
+    instance.v[0x0] = 0x12;
+    instance.v[0x1] = 0x34;
+    instance.memory[0x200] = 0xF1;
+    instance.memory[0x201] = 0x75;
+    instance.memory[0x202] = 0xF1;
+    instance.memory[0x203] = 0x85;
+    instance.step().unwrap();
+    instance.v[0x0] = 0x0;
+    instance.v[0x1] = 0x0;
+
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.v[0x0], 0x12);
+    assert_hex::assert_eq_hex!(instance.v[0x1], 0x34);
+}
+
+fn xochip() -> Chip8 {
+    let mut instance = Chip8::new(Quirks::xochip());
+    instance.set_mode(Mode::XoChip);
+    instance
+}
+
+#[test]
+fn xochip_memory() {
+    let mut instance = xochip();
+    assert_eq!(instance.memory.len(), 0x10000);
+    instance.memory[0x200] = 0xF0;
+    instance.memory[0x201] = 0x00;
+    instance.memory[0x202] = 0xFF;
+    instance.memory[0x203] = 0xF0;
+    instance.memory[0xFFF0] = 0xAB;
+    instance.memory[0x204] = 0xF0;
+    instance.memory[0x205] = 0x65;
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.i, 0xFFF0);
+    assert_hex::assert_eq_hex!(instance.pc, 0x204);
+
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.v[0x0], 0xAB);
+}
+
+#[test]
+fn xochip_skip_long_instruction() {
+    let mut instance = xochip();
+    instance.memory[0x200] = 0x30;
+    instance.memory[0x201] = 0x00;
+    instance.memory[0x202] = 0xF0;
+    instance.memory[0x203] = 0x00;
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x206);
+}
+
+#[test]
+fn op_5xy2_5xy3() {
+    let mut instance = xochip();
+    instance.v[0x1] = 0x11;
+    instance.v[0x2] = 0x22;
+    instance.v[0x3] = 0x33;
+    instance.i = 0x300;
+    instance.memory[0x200] = 0x51;
+    instance.memory[0x201] = 0x32;
+    instance.memory[0x202] = 0x53;
+    instance.memory[0x203] = 0x13;
+    instance.step().unwrap();
+    assert_eq!(instance.memory[0x300..0x303], [0x11, 0x22, 0x33]);
+    assert_hex::assert_eq_hex!(instance.i, 0x300);
+
+    // loading in reverse order
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.v[0x3], 0x11);
+    assert_hex::assert_eq_hex!(instance.v[0x2], 0x22);
+    assert_hex::assert_eq_hex!(instance.v[0x1], 0x33);
+}
+
+#[test]
+fn op_fn01_planes() {
+    let mut instance = xochip();
+    instance.i = 0x300;
+    instance.memory[0x300] = 0x80;
+    instance.memory[0x301] = 0xC0;
+    // select both planes, then draw a 1 row sprite
+    instance.memory[0x200] = 0xF3;
+    instance.memory[0x201] = 0x01;
+    instance.memory[0x202] = 0xD0;
+    instance.memory[0x203] = 0x01;
+    // select plane 2 and clear it
+    instance.memory[0x204] = 0xF2;
+    instance.memory[0x205] = 0x01;
+    instance.memory[0x206] = 0x00;
+    instance.memory[0x207] = 0xE0;
+    instance.step().unwrap();
+    instance.step().unwrap();
+    assert_eq!(instance.display.row(0)[0..3], [0x3, 0x2, 0x0]);
+
+    instance.step().unwrap();
+    instance.step().unwrap();
+    assert_eq!(instance.display.row(0)[0..3], [0x1, 0x0, 0x0]);
+}
+
+#[test]
+fn op_f002_fx3a() {
+    let mut instance = xochip();
+    instance.i = 0x300;
+    for byte in 0..16 {
+        instance.memory[0x300 + byte] = byte as u8;
+    }
+    instance.v[0x4] = 0x70;
+    instance.memory[0x200] = 0xF0;
+    instance.memory[0x201] = 0x02;
+    instance.memory[0x202] = 0xF4;
+    instance.memory[0x203] = 0x3A;
+    instance.step().unwrap();
+    instance.step().unwrap();
+    assert_eq!(instance.audio_pattern()[15], 15);
+    assert_hex::assert_eq_hex!(instance.pitch(), 0x70);
+}
+
+#[test]
+fn op_00dn() {
+    let mut instance = xochip();
+    instance.display.set(3, 5, 1);
+    instance.memory[0x200] = 0x00;
+    instance.memory[0x201] = 0xD2;
+    instance.step().unwrap();
+    assert_eq!(instance.display.get(3, 5), 0);
+    assert_eq!(instance.display.get(3, 3), 1);
+}
+
+#[test]
+fn run_frame_ticks_timers_once() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.delay_timer = 0x10;
+    instance.sound_timer = 0x10;
+    // 7001 (v0 += 1) repeated
+    for address in (0x200..0x300).step_by(2) {
+        instance.memory[address] = 0x70;
+        instance.memory[address + 1] = 0x01;
+    }
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.delay_timer, 0x10);
+
+    instance.run_frame(20).unwrap();
+    assert_hex::assert_eq_hex!(instance.v[0x0], 21);
+    assert_hex::assert_eq_hex!(instance.delay_timer, 0xF);
+    assert_hex::assert_eq_hex!(instance.sound_timer, 0xF);
+}
+
+#[test]
+fn run_frame_display_wait() {
+    let quirks = Quirks {
+        display_wait: true,
+        ..Default::default()
+    };
+    let mut instance = Chip8::new(quirks);
+    // 2 sprites: only one is drawn per frame
+    for address in (0x200..0x204).step_by(2) {
+        instance.memory[address] = 0xD0;
+        instance.memory[address + 1] = 0x01;
+    }
+    // then loop forever
+    instance.memory[0x204] = 0x12;
+    instance.memory[0x205] = 0x04;
+    instance.run_frame(10).unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x200);
+    instance.run_frame(10).unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x202);
+    instance.run_frame(10).unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x204);
+}
+
+#[test]
+fn error_unknown_opcode() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.memory[0x200] = 0xFF;
+    instance.memory[0x201] = 0xFF;
+    assert!(matches!(
+        instance.step(),
+        Err(Chip8Error::UnknownOpcode {
+            pc: 0x200,
+            opcode: 0xFFFF
+        })
+    ));
+}
+
+#[test]
+fn error_stack_overflow() {
+    let mut instance = Chip8::new(Quirks::default());
+    // call 0x200 forever
+    instance.memory[0x200] = 0x22;
+    instance.memory[0x201] = 0x00;
+    for _ in 0..16 {
+        instance.step().unwrap();
+    }
+    assert!(matches!(instance.step(), Err(Chip8Error::StackOverflow)));
+}
+
+#[test]
+fn error_stack_underflow() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.memory[0x200] = 0x00;
+    instance.memory[0x201] = 0xEE;
+    assert!(matches!(instance.step(), Err(Chip8Error::StackUnderflow)));
+}
+
+#[test]
+fn error_fetch_out_of_bounds() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.pc = 0xFFF;
+    assert!(matches!(
+        instance.step(),
+        Err(Chip8Error::MemoryOutOfBounds { addr: 0x1000 })
+    ));
+}
+
+#[test]
+fn error_fx33_out_of_bounds() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.i = 0xFFF;
+    instance.memory[0x200] = 0xF0;
+    instance.memory[0x201] = 0x33;
+    assert!(matches!(
+        instance.step(),
+        Err(Chip8Error::MemoryOutOfBounds { addr: 0x1000 })
+    ));
+    // the instruction didn't complete
+    assert_hex::assert_eq_hex!(instance.pc, 0x200);
+}
+
+#[test]
+fn error_dxyn_out_of_bounds() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.i = 0xFFE;
+    instance.memory[0xFFE] = 0xFF;
+    instance.memory[0xFFF] = 0xFF;
+    instance.memory[0x200] = 0xD0;
+    instance.memory[0x201] = 0x0F;
+    assert!(matches!(
+        instance.step(),
+        Err(Chip8Error::MemoryOutOfBounds { addr: 0x1000 })
+    ));
+}
+
+#[test]
+fn fx1e_wraps() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.i = 0xFFFF;
+    instance.v[0x0] = 0x2;
+    instance.memory[0x200] = 0xF0;
+    instance.memory[0x201] = 0x1E;
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.i, 0x1);
+}
+
+#[test]
+fn fx29_masks_digit() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.v[0x0] = 0xFA;
+    instance.memory[0x200] = 0xF0;
+    instance.memory[0x201] = 0x29;
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.i, 0xA * 5);
+}
+
+#[test]
+fn pc_wraps_at_end_of_memory() {
+    let mut instance = xochip();
+    instance.pc = 0xFFFE;
+    instance.memory[0xFFFE] = 0x60;
+    instance.memory[0xFFFF] = 0x01;
+    instance.step().unwrap();
+    assert_hex::assert_eq_hex!(instance.pc, 0x0);
+}
+
+#[test]
+fn rom_too_large() {
+    let path = std::env::temp_dir().join("chip8_rom_too_large.ch8");
+    std::fs::write(&path, vec![0u8; 0x1000]).unwrap();
+    let mut instance = Chip8::new(Quirks::default());
+    let result = instance.load_rom(path.to_str().unwrap());
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(
+        result,
+        Err(Chip8Error::RomTooLarge { size: 0x1000 })
+    ));
+}
+
+// v0 = random & 0xFF, 8 times
+fn random_bytes(seed: u64) -> Vec<u8> {
+    let mut instance = Chip8::new_with_seed(Quirks::default(), seed);
+    instance.memory[0x200] = 0xC0;
+    instance.memory[0x201] = 0xFF;
+    instance.memory[0x202] = 0x12;
+    instance.memory[0x203] = 0x00;
+    (0..8)
+        .map(|_| {
+            instance.step().unwrap();
+            instance.step().unwrap();
+            instance.v[0x0]
+        })
+        .collect()
+}
+
+#[test]
+fn op_cxkk_seeded() {
+    assert_eq!(random_bytes(1234), random_bytes(1234));
+    assert_ne!(random_bytes(0), random_bytes(1));
+}
+
+#[test]
+fn op_cxkk_masks() {
+    let mut instance = Chip8::new_with_seed(Quirks::default(), 7);
+    for _ in 0..32 {
+        instance.pc = 0x200;
+        instance.memory[0x200] = 0xC0;
+        instance.memory[0x201] = 0x0F;
+        instance.step().unwrap();
+        assert_eq!(instance.v[0x0] & 0xF0, 0);
+    }
+}
+
+// keeps every access reported
+struct AccessLog(Rc<RefCell<Vec<(usize, Access, u8)>>>);
+
+impl MemoryObserver for AccessLog {
+    fn on_access(&mut self, addr: usize, access: Access, value: u8) {
+        self.0.borrow_mut().push((addr, access, value));
+    }
+}
+
+#[test]
+fn observer_sees_memory_accesses() {
+    let mut instance = Chip8::new(Quirks::default());
+    let log = Rc::new(RefCell::new(Vec::new()));
+    instance.set_observer(Some(Box::new(AccessLog(Rc::clone(&log)))));
+    instance.i = 0x300;
+    instance.v[0x0] = 0xAB;
+    instance.memory[0x200] = 0xF0;
+    instance.memory[0x201] = 0x55;
+    instance.memory[0x202] = 0xF0;
+    instance.memory[0x203] = 0x65;
+    instance.step().unwrap();
+    instance.step().unwrap();
+
+    assert_eq!(
+        *log.borrow(),
+        [
+            (0x200, Access::Fetch, 0xF0),
+            (0x201, Access::Fetch, 0x55),
+            (0x300, Access::Write, 0xAB),
+            (0x202, Access::Fetch, 0xF0),
+            (0x203, Access::Fetch, 0x65),
+            (0x300, Access::Read, 0xAB),
+        ]
+    );
+}
+
+#[test]
+fn skipping_fetches_nothing() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.set_mode(Mode::XoChip);
+    let log = Rc::new(RefCell::new(Vec::new()));
+    instance.set_observer(Some(Box::new(AccessLog(Rc::clone(&log)))));
+    // se v0, 0 skips the f000 nnnn after it
+    instance.memory[0x200..0x206].copy_from_slice(&[0x30, 0x00, 0xF0, 0x00, 0x12, 0x34]);
+    instance.step().unwrap();
+
+    assert_eq!(instance.pc, 0x206);
+    assert_eq!(
+        *log.borrow(),
+        [(0x200, Access::Fetch, 0x30), (0x201, Access::Fetch, 0x00)]
+    );
+}
@@ -0,0 +1,260 @@
+use chip8_emulator::debugger::parse_number;
+use chip8_emulator::palette::{self, Rgb};
+use chip8_emulator::persistence::Flicker;
+use chip8_emulator::trace::Format;
+use chip8_emulator::tui::Glyphs;
+use chip8_emulator::viewport::Scaling;
+use chip8_emulator::{quirks, Mode, Palette, Quirks};
+use std::convert::TryFrom;
+use std::env;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: chip8-emulator <rom> [options]
+Options:
+    --profile <name>    the interpreter to emulate: default, vip, chip48, schip or xochip
+    --ipf <n>           instructions executed per frame (60 frames per second)
+    --seed <n>          seed of the random number generator, for reproducible runs
+    --record <file>     record the keys pressed to a movie file, written on exit
+    --play <file>       play back a movie file, replacing the keyboard and the options above
+    --debug             start paused, with a debugger reading commands from the terminal
+    --trace <file>      write every instruction executed and the registers it changed
+    --trace-format <f>  text (by default) or binary, read back with chip8-trace
+    --trace-range <a-b> only trace the instructions between two addresses: 0x200-0x2FF
+    --config <file>     the key bindings, ~/.config/chip8-emulator/config.ini by default
+    --palette <p>       the colors: mono, green, amber, lcd, or 2 to 4 hex RGB colors
+                        \"#000000,#FFFFFF\" (off, plane 1, plane 2, both planes)
+    --scale <n>         the size of the CHIP-8 pixels when the window opens, 20 by default
+    --scaling <mode>    integer (by default) or fit: the screen scales with the window in
+                        whole multiples, or as large as it fits
+    --border <color>    the hex RGB color around the screen, its background color by default
+    --flicker <mode>    off (by default), decay: the pixels fade out like phosphor, or or: the
+                        pixels of the previous frame stay lit
+    --flicker-strength <s>
+                        0 - 1: how much of the previous frames shows
+    --tui <glyphs>      run in the terminal instead of a window, drawing the pixels with half
+                        (half blocks, 2 pixels per character) or braille (8 per character)";
+
+// The command line options
+pub struct Options {
+    pub rom: String,
+    pub mode: Mode,
+    pub quirks: Quirks,
+    // instructions per frame
+    pub ipf: usize,
+    // the random number generator seed, picked at random when not given
+    pub seed: u64,
+    // the movie file to record to
+    pub record: Option<String>,
+    // the movie file to play back
+    pub play: Option<String>,
+    pub debug: bool,
+    // the file to write the trace to
+    pub trace: Option<String>,
+    pub trace_format: Format,
+    // the addresses of the instructions traced, all of them when not given
+    pub trace_range: Option<RangeInclusive<u16>>,
+    // the key bindings file, the default bindings when there's none
+    pub config: Option<String>,
+    // replace the [display] settings of the config
+    pub palette: Option<Palette>,
+    // only the window has these
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    pub scale: Option<u32>,
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    pub scaling: Option<Scaling>,
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    pub border: Option<Rgb>,
+    pub flicker: Option<Flicker>,
+    pub flicker_strength: Option<f32>,
+    // the terminal frontend, the window when not given
+    pub tui: Option<Glyphs>,
+}
+
+impl Options {
+    // parse the command line arguments (without the program name)
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut rom = None;
+        let mut mode = Mode::Chip8;
+        let mut quirks = Quirks::default();
+        let mut ipf = None;
+        let mut seed = None;
+        let mut record = None;
+        let mut play = None;
+        let mut debug = false;
+        let mut trace = None;
+        let mut trace_format = Format::Text;
+        let mut trace_range = None;
+        let mut config = None;
+        let mut palette = None;
+        let mut scale = None;
+        let mut scaling = None;
+        let mut border = None;
+        let mut flicker = None;
+        let mut flicker_strength = None;
+        let mut tui = None;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--profile" => {
+                    let name = Options::value(arg, args.next())?;
+                    match quirks::profile(name) {
+                        Some(profile) => {
+                            mode = profile.0;
+                            quirks = profile.1;
+                        }
+                        None => return Err(format!("Unknown interpreter profile: {}", name)),
+                    }
+                }
+                "--ipf" => {
+                    let value = Options::value(arg, args.next())?;
+                    match value.parse() {
+                        Ok(n) if n > 0 => ipf = Some(n),
+                        _ => return Err(format!("Invalid instructions per frame: {}", value)),
+                    }
+                }
+                "--seed" => {
+                    let value = Options::value(arg, args.next())?;
+                    match parse_number(value) {
+                        Some(n) => seed = Some(n),
+                        None => return Err(format!("Invalid seed: {}", value)),
+                    }
+                }
+                "--record" => record = Some(Options::value(arg, args.next())?.to_string()),
+                "--play" => play = Some(Options::value(arg, args.next())?.to_string()),
+                "--debug" => debug = true,
+                "--trace" => trace = Some(Options::value(arg, args.next())?.to_string()),
+                "--trace-format" => {
+                    trace_format = match Options::value(arg, args.next())? {
+                        "text" => Format::Text,
+                        "binary" => Format::Binary,
+                        name => return Err(format!("Unknown trace format: {}", name)),
+                    }
+                }
+                "--trace-range" => {
+                    let value = Options::value(arg, args.next())?;
+                    match parse_range(value) {
+                        Some(range) => trace_range = Some(range),
+                        None => return Err(format!("Invalid address range: {}", value)),
+                    }
+                }
+                "--config" => config = Some(Options::value(arg, args.next())?.to_string()),
+                "--palette" => palette = Some(Palette::parse(Options::value(arg, args.next())?)?),
+                "--scale" => {
+                    let value = Options::value(arg, args.next())?;
+                    match value.parse() {
+                        Ok(n) if n > 0 => scale = Some(n),
+                        _ => return Err(format!("Invalid scale: {}", value)),
+                    }
+                }
+                "--scaling" => {
+                    let value = Options::value(arg, args.next())?;
+                    match Scaling::parse(value) {
+                        Some(mode) => scaling = Some(mode),
+                        None => return Err(format!("Unknown scaling: {}", value)),
+                    }
+                }
+                "--border" => {
+                    let value = Options::value(arg, args.next())?;
+                    match palette::parse_color(value) {
+                        Some(color) => border = Some(color),
+                        None => return Err(format!("Invalid color: {}", value)),
+                    }
+                }
+                "--flicker" => {
+                    let value = Options::value(arg, args.next())?;
+                    match Flicker::parse(value) {
+                        Some(mode) => flicker = Some(mode),
+                        None => return Err(format!("Unknown flicker mode: {}", value)),
+                    }
+                }
+                "--flicker-strength" => {
+                    let value = Options::value(arg, args.next())?;
+                    match value.parse() {
+                        Ok(s) if (0.0..=1.0).contains(&s) => flicker_strength = Some(s),
+                        _ => return Err(format!("Invalid flicker strength: {}", value)),
+                    }
+                }
+                "--tui" => {
+                    let value = Options::value(arg, args.next())?;
+                    match Glyphs::parse(value) {
+                        Some(glyphs) => tui = Some(glyphs),
+                        None => return Err(format!("Unknown glyphs: {}", value)),
+                    }
+                }
+                _ if arg.starts_with("--") => return Err(format!("Unknown option: {}", arg)),
+                _ if rom.is_none() => rom = Some(arg.clone()),
+                _ => return Err(format!("Unexpected argument: {}", arg)),
+            }
+        }
+
+        if record.is_some() && play.is_some() {
+            return Err("Can't record and play a movie at the same time".to_string());
+        }
+        if debug && record.is_some() {
+            // stepping through instructions would make the movie impossible to replay
+            return Err("Can't record a movie while debugging".to_string());
+        }
+        if debug && trace.is_some() {
+            return Err("Can't trace while debugging".to_string());
+        }
+        if debug && tui.is_some() {
+            // the debugger reads its commands from the terminal too
+            return Err("Can't debug in the terminal frontend".to_string());
+        }
+
+        Ok(Options {
+            rom: rom.ok_or("No ROM given")?,
+            mode,
+            quirks,
+            ipf: ipf.unwrap_or_else(|| mode.default_ipf()),
+            seed: seed.unwrap_or_else(rand::random),
+            record,
+            play,
+            debug,
+            trace,
+            trace_format,
+            trace_range,
+            config: config.or_else(default_config),
+            palette,
+            scale,
+            scaling,
+            border,
+            flicker,
+            flicker_strength,
+            tui,
+        })
+    }
+
+    // the value following an option
+    fn value<'a>(option: &str, value: Option<&'a String>) -> Result<&'a str, String> {
+        value
+            .map(String::as_str)
+            .ok_or(format!("Missing value for {}", option))
+    }
+}
+
+// $XDG_CONFIG_HOME/chip8-emulator/config.ini (~/.config when not set), if it exists
+fn default_config() -> Option<String> {
+    let dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    let path = dir.join("chip8-emulator").join("config.ini");
+    if !path.is_file() {
+        return None;
+    }
+    path.to_str().map(str::to_string)
+}
+
+// two addresses separated by a dash: 0x200-0x2FF
+fn parse_range(value: &str) -> Option<RangeInclusive<u16>> {
+    let (start, end) = value.split_once('-')?;
+    let start = u16::try_from(parse_number(start.trim())?).ok()?;
+    let end = u16::try_from(parse_number(end.trim())?).ok()?;
+    if start > end {
+        return None;
+    }
+    Some(start..=end)
+}
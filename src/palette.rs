@@ -0,0 +1,135 @@
+// Display palettes: the colors of the pixel values, for the window and the screenshots.
+//
+// A palette is a preset name, or custom colors written as hex RGB and separated by commas:
+//     green                       a preset
+//     #101010, #E0E0E0            off and plane 1, the other two are blended from them
+//     000000, FF0000, 0000FF      off, plane 1 and plane 2, both planes is their average
+//     000000, FF0000, 0000FF, FFFFFF
+use std::fmt::Write;
+
+pub type Rgb = [u8; 3];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+    // the color of each pixel value: off, plane 1, plane 2, both planes
+    pub colors: [Rgb; 4],
+}
+
+// the presets by name, the first one is the default
+pub const PRESETS: [(&str, Palette); 4] = [
+    (
+        "mono",
+        Palette {
+            colors: [[0, 0, 0], [255, 255, 255], [170, 170, 170], [85, 85, 85]],
+        },
+    ),
+    // a green phosphor CRT
+    (
+        "green",
+        Palette {
+            colors: [[5, 20, 5], [51, 255, 102], [36, 177, 73], [20, 98, 40]],
+        },
+    ),
+    // an amber monochrome monitor
+    (
+        "amber",
+        Palette {
+            colors: [[20, 10, 0], [255, 176, 0], [177, 121, 0], [98, 65, 0]],
+        },
+    ),
+    // the four shades of a handheld green LCD
+    (
+        "lcd",
+        Palette {
+            colors: [[155, 188, 15], [15, 56, 15], [48, 98, 48], [139, 172, 15]],
+        },
+    ),
+];
+
+impl Default for Palette {
+    fn default() -> Palette {
+        PRESETS[0].1
+    }
+}
+
+impl Palette {
+    // A preset name or custom colors
+    pub fn parse(value: &str) -> Result<Palette, String> {
+        if let Some(palette) = preset(value) {
+            return Ok(palette);
+        }
+        let mut colors = Vec::new();
+        for color in value.split(',').map(str::trim) {
+            colors.push(parse_color(color).ok_or_else(|| {
+                if !value.contains([',', '#']) {
+                    format!("Unknown palette: {}", value)
+                } else {
+                    format!("Invalid color (6 hex digits): {}", color)
+                }
+            })?);
+        }
+        let (off, on) = match colors[..] {
+            [off, on, ..] if colors.len() <= 4 => (off, on),
+            _ => return Err(format!("Expected 2 to 4 colors, found {}", colors.len())),
+        };
+        let plane2 = colors
+            .get(2)
+            .copied()
+            .unwrap_or_else(|| blend(off, on, 2, 3));
+        let both = match colors.get(3) {
+            Some(&both) => both,
+            None if colors.len() == 3 => blend(on, plane2, 1, 2),
+            None => blend(off, on, 1, 3),
+        };
+        Ok(Palette {
+            colors: [off, on, plane2, both],
+        })
+    }
+
+    pub fn color(&self, pixel: u8) -> Rgb {
+        self.colors[pixel as usize & 0x3]
+    }
+
+    // The name of the preset, or the colors
+    pub fn name(&self) -> String {
+        if let Some((name, _)) = PRESETS.iter().find(|(_, preset)| preset == self) {
+            return name.to_string();
+        }
+        let mut text = String::new();
+        for (i, [r, g, b]) in self.colors.iter().enumerate() {
+            let separator = if i == 0 { "" } else { ", " };
+            let _ = write!(text, "{}#{:02X}{:02X}{:02X}", separator, r, g, b);
+        }
+        text
+    }
+}
+
+pub fn preset(name: &str) -> Option<Palette> {
+    PRESETS
+        .iter()
+        .find(|(preset, _)| preset.eq_ignore_ascii_case(name))
+        .map(|&(_, palette)| palette)
+}
+
+// RRGGBB, with or without #
+pub fn parse_color(text: &str) -> Option<Rgb> {
+    let digits = text.strip_prefix('#').unwrap_or(text);
+    if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).ok();
+    Some([channel(0)?, channel(1)?, channel(2)?])
+}
+
+// the color part / whole of the way from a to b
+fn blend(a: Rgb, b: Rgb, part: i32, whole: i32) -> Rgb {
+    let mut color = [0; 3];
+    for (channel, (&a, &b)) in color.iter_mut().zip(a.iter().zip(b.iter())) {
+        *channel = (a as i32 + (b as i32 - a as i32) * part / whole) as u8;
+    }
+    color
+}
+
+#[cfg(test)]
+#[path = "./palette_tests.rs"]
+mod palette_tests;
@@ -0,0 +1,46 @@
+use super::*;
+
+#[test]
+fn presets() {
+    assert_eq!(Palette::default(), preset("mono").unwrap());
+    assert_eq!(Palette::parse("Amber").unwrap(), preset("amber").unwrap());
+    for (name, palette) in PRESETS.iter() {
+        assert_eq!(&palette.name(), name);
+    }
+}
+
+#[test]
+fn custom_colors() {
+    // two colors blend like the default palette
+    let palette = Palette::parse("#000000, #FFFFFF").unwrap();
+    assert_eq!(palette, Palette::default());
+
+    let palette = Palette::parse("000000,FF0000,0000fe").unwrap();
+    assert_eq!(palette.colors[2], [0, 0, 254]);
+    assert_eq!(palette.colors[3], [128, 0, 127]);
+    assert_eq!(palette.color(2), [0, 0, 254]);
+
+    let palette = Palette::parse("#010203, #040506, #070809, #0A0B0C").unwrap();
+    assert_eq!(palette.color(3), [10, 11, 12]);
+    assert_eq!(palette.name(), "#010203, #040506, #070809, #0A0B0C");
+}
+
+#[test]
+fn invalid_palettes() {
+    assert_eq!(
+        Palette::parse("blue"),
+        Err("Unknown palette: blue".to_string())
+    );
+    assert_eq!(
+        Palette::parse("#000000, #FFF"),
+        Err("Invalid color (6 hex digits): #FFF".to_string())
+    );
+    assert_eq!(
+        Palette::parse("#000000"),
+        Err("Expected 2 to 4 colors, found 1".to_string())
+    );
+    assert_eq!(
+        Palette::parse("0,0,0,0,0").map(|_| ()),
+        Err("Invalid color (6 hex digits): 0".to_string())
+    );
+}
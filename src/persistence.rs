@@ -0,0 +1,118 @@
+// Flicker reduction: CHIP-8 games erase and redraw their sprites by XORing them, so a moving
+// sprite is off for part of its frames and blinks. The screens of the time hid it, their phosphor
+// glowed for a while after the beam left. The screen is processed on the CPU, into the colors
+// shown, before any scaling:
+//     decay   a pixel turning off fades to the background, keeping strength of its color
+//             every frame
+//     or      the pixels lit on the previous frame are still shown, at strength of their color
+// A strength of 0 shows the frames as they are.
+use crate::framebuffer::Framebuffer;
+use crate::palette::{Palette, Rgb};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Flicker {
+    Off,
+    Decay,
+    Or,
+}
+
+impl Flicker {
+    pub fn parse(name: &str) -> Option<Flicker> {
+        match name {
+            "off" => Some(Flicker::Off),
+            "decay" => Some(Flicker::Decay),
+            "or" => Some(Flicker::Or),
+            _ => None,
+        }
+    }
+
+    // the strength when none is given: a glow over a few frames, the plain OR of two frames
+    pub fn default_strength(self) -> f32 {
+        match self {
+            Flicker::Off => 0.0,
+            Flicker::Decay => 0.6,
+            Flicker::Or => 1.0,
+        }
+    }
+}
+
+impl fmt::Display for Flicker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Flicker::Off => write!(f, "off"),
+            Flicker::Decay => write!(f, "decay"),
+            Flicker::Or => write!(f, "or"),
+        }
+    }
+}
+
+pub struct Persistence {
+    flicker: Flicker,
+    // 0 - 1
+    strength: f32,
+    // the colors shown on the last frame, row by row
+    image: Vec<Rgb>,
+    // the pixel values of the last frame
+    previous: Vec<u8>,
+    // set while the image differs from the framebuffer
+    fading: bool,
+}
+
+impl Persistence {
+    pub fn new(flicker: Flicker, strength: f32) -> Persistence {
+        Persistence {
+            flicker,
+            strength: strength.clamp(0.0, 1.0),
+            image: Vec::new(),
+            previous: Vec::new(),
+            fading: false,
+        }
+    }
+
+    // Process a frame: the colors to show, row by row. Call it on every frame while fading()
+    pub fn render(&mut self, framebuffer: &Framebuffer, palette: &Palette) -> &[Rgb] {
+        let pixels = framebuffer.pixels();
+        if self.previous.len() != pixels.len() {
+            // the resolution changed, nothing to fade from
+            self.previous = pixels.to_vec();
+            self.image = pixels.iter().map(|&pixel| palette.color(pixel)).collect();
+        }
+
+        let background = palette.color(0);
+        self.fading = false;
+        for (i, &pixel) in pixels.iter().enumerate() {
+            let color = match self.flicker {
+                _ if pixel != 0 => palette.color(pixel),
+                Flicker::Off => background,
+                Flicker::Decay => blend(background, self.image[i], self.strength),
+                Flicker::Or => blend(background, palette.color(self.previous[i]), self.strength),
+            };
+            self.fading |= color != palette.color(pixel);
+            self.image[i] = color;
+        }
+        self.previous.copy_from_slice(pixels);
+        &self.image
+    }
+
+    // whether the image shown still differs from the framebuffer: the next frames change it even
+    // if the framebuffer doesn't
+    pub fn fading(&self) -> bool {
+        self.fading
+    }
+}
+
+// strength of the way from a to b
+fn blend(a: Rgb, b: Rgb, strength: f32) -> Rgb {
+    let mut color = a;
+    for (channel, &b) in color.iter_mut().zip(b.iter()) {
+        // the difference is truncated, towards a, so that a fade ends
+        let difference = ((b as f32 - *channel as f32) * strength) as i32;
+        *channel = (*channel as i32 + difference) as u8;
+    }
+    color
+}
+
+#[cfg(test)]
+#[path = "./persistence_tests.rs"]
+mod persistence_tests;
@@ -0,0 +1,84 @@
+use super::*;
+
+// one lit pixel out of 2
+fn screen(lit: bool) -> Framebuffer {
+    Framebuffer::from_pixels(2, 1, vec![lit as u8, 0])
+}
+
+#[test]
+fn off_shows_the_frames() {
+    let palette = Palette::default();
+    let mut persistence = Persistence::new(Flicker::Off, 0.5);
+    persistence.render(&screen(true), &palette);
+    assert_eq!(persistence.render(&screen(false), &palette), [[0; 3]; 2]);
+    assert!(!persistence.fading());
+}
+
+#[test]
+fn decay_fades_to_the_background() {
+    let palette = Palette::default();
+    let mut persistence = Persistence::new(Flicker::Decay, 0.5);
+    assert_eq!(persistence.render(&screen(true), &palette)[0], [255; 3]);
+    assert!(!persistence.fading());
+
+    let mut levels = Vec::new();
+    loop {
+        levels.push(persistence.render(&screen(false), &palette)[0][0]);
+        if !persistence.fading() {
+            break;
+        }
+    }
+    assert_eq!(levels, [127, 63, 31, 15, 7, 3, 1, 0]);
+    // lit again at once
+    assert_eq!(persistence.render(&screen(true), &palette)[0], [255; 3]);
+}
+
+#[test]
+fn decay_towards_a_light_background() {
+    let palette = crate::palette::preset("lcd").unwrap();
+    let mut persistence = Persistence::new(Flicker::Decay, 0.9);
+    persistence.render(&screen(true), &palette);
+    for _ in 0..1000 {
+        persistence.render(&screen(false), &palette);
+    }
+    assert!(!persistence.fading());
+    assert_eq!(
+        persistence.render(&screen(false), &palette)[0],
+        palette.color(0)
+    );
+}
+
+#[test]
+fn or_shows_the_previous_frame() {
+    let palette = Palette::default();
+    let mut persistence = Persistence::new(Flicker::Or, 1.0);
+    persistence.render(&screen(true), &palette);
+    // the sprite erased to be redrawn is still shown
+    assert_eq!(persistence.render(&screen(false), &palette)[0], [255; 3]);
+    assert!(persistence.fading());
+    assert_eq!(persistence.render(&screen(false), &palette)[0], [0; 3]);
+    assert!(!persistence.fading());
+
+    let mut persistence = Persistence::new(Flicker::Or, 0.5);
+    persistence.render(&screen(true), &palette);
+    assert_eq!(persistence.render(&screen(false), &palette)[0], [127; 3]);
+}
+
+#[test]
+fn resolution_change_starts_over() {
+    let palette = Palette::default();
+    let mut persistence = Persistence::new(Flicker::Decay, 0.5);
+    persistence.render(&screen(true), &palette);
+    let hires = Framebuffer::new(4, 2);
+    assert_eq!(persistence.render(&hires, &palette), [[0; 3]; 8]);
+    assert!(!persistence.fading());
+}
+
+#[test]
+fn parse_flicker() {
+    assert_eq!(Flicker::parse("decay"), Some(Flicker::Decay));
+    assert_eq!(Flicker::parse("or"), Some(Flicker::Or));
+    assert_eq!(Flicker::parse("off"), Some(Flicker::Off));
+    assert_eq!(Flicker::parse("blur"), None);
+    assert_eq!(Flicker::Or.to_string(), "or");
+}
@@ -0,0 +1,36 @@
+use super::*;
+
+#[test]
+fn checksums() {
+    assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+}
+
+#[test]
+fn encode_small_image() {
+    // 2x1: a red pixel and a blue one
+    let png = encode(2, 1, &[255, 0, 0, 0, 0, 255]);
+    assert_eq!(png[..8], SIGNATURE);
+    // IHDR
+    assert_eq!(png[8..16], [0, 0, 0, 13, b'I', b'H', b'D', b'R']);
+    assert_eq!(png[16..29], [0, 0, 0, 2, 0, 0, 0, 1, 8, 2, 0, 0, 0]);
+    // IDAT: zlib header, one final stored block of the 7 bytes of the row, adler32
+    assert_eq!(png[33..41], [0, 0, 0, 18, b'I', b'D', b'A', b'T']);
+    assert_eq!(png[41..48], [0x78, 0x01, 1, 7, 0, 0xF8, 0xFF]);
+    assert_eq!(png[48..55], [0, 255, 0, 0, 0, 0, 255]);
+    assert_eq!(
+        png[png.len() - 12..png.len() - 4],
+        [0, 0, 0, 0, b'I', b'E', b'N', b'D']
+    );
+}
+
+#[test]
+fn zlib_splits_large_data_in_blocks() {
+    let data = vec![7; MAX_BLOCK + 10];
+    let stream = zlib(&data);
+    assert_eq!(stream.len(), 2 + 5 + MAX_BLOCK + 5 + 10 + 4);
+    // the first block isn't final, the second one is
+    assert_eq!(stream[2], 0);
+    assert_eq!(stream[2 + 5 + MAX_BLOCK], 1);
+}
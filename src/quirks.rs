@@ -0,0 +1,153 @@
+// The original CHIP-8 spec leaves a few opcodes ambiguous, and the interpreters that came
+// after the COSMAC VIP (CHIP-48, SUPER-CHIP...) each picked their own interpretation.
+// A ROM usually only runs correctly with the quirks of the interpreter it was written for.
+use crate::chip8::Mode;
+
+// The mode and the quirks of an interpreter, by name
+pub fn profile(name: &str) -> Option<(Mode, Quirks)> {
+    match name {
+        "default" => Some((Mode::Chip8, Quirks::default())),
+        "vip" => Some((Mode::Chip8, Quirks::cosmac_vip())),
+        "chip48" => Some((Mode::Chip8, Quirks::chip48())),
+        "schip" => Some((Mode::SuperChip, Quirks::superchip())),
+        "xochip" => Some((Mode::XoChip, Quirks::xochip())),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quirks {
+    // 8xy6/8xye: vx = vy shifted, instead of shifting vx in place
+    pub shift_uses_vy: bool,
+    // fx55/fx65: I is incremented by x + 1 after the store/load
+    pub load_store_increments_i: bool,
+    // bnnn: jumps to xnn + vx instead of nnn + v0
+    pub jump_uses_vx: bool,
+    // 8xy1/8xy2/8xy3: vf is reset to 0 after the logic operation
+    pub vf_reset: bool,
+    // dxyn: pixels going past the edge of the screen are clipped instead of wrapped around
+    pub clip_sprites: bool,
+    // dxyn: waits for the vertical blank before drawing (at most one sprite per frame)
+    pub display_wait: bool,
+    // fx0a: the key is stored once it's released, instead of as soon as it's pressed
+    pub key_wait_on_release: bool,
+    // fx0a: a tone plays while the key is held, as the COSMAC VIP does
+    pub key_wait_beep: bool,
+    // dxyn in hires: vf is the number of rows that collided or were clipped at the bottom,
+    // instead of 1 on any collision
+    pub collision_counts_rows: bool,
+}
+
+impl Default for Quirks {
+    // The modern interpretation most ROMs expect: shifts and fx55/fx65 as on CHIP-48, bnnn as on
+    // the VIP, sprites clipped at the edges and fx0a firing when the key is released
+    fn default() -> Quirks {
+        Quirks {
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            jump_uses_vx: false,
+            vf_reset: false,
+            clip_sprites: true,
+            display_wait: false,
+            key_wait_on_release: true,
+            key_wait_beep: false,
+            collision_counts_rows: false,
+        }
+    }
+}
+
+impl Quirks {
+    // all the quirks packed in a bitmask, in the order of the fields
+    pub fn to_bits(&self) -> u16 {
+        [
+            self.shift_uses_vy,
+            self.load_store_increments_i,
+            self.jump_uses_vx,
+            self.vf_reset,
+            self.clip_sprites,
+            self.display_wait,
+            self.key_wait_on_release,
+            self.key_wait_beep,
+            self.collision_counts_rows,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (bit, &quirk)| bits | (quirk as u16) << bit)
+    }
+
+    pub fn from_bits(bits: u16) -> Quirks {
+        let quirk = |bit: u16| bits & (1 << bit) != 0;
+        Quirks {
+            shift_uses_vy: quirk(0),
+            load_store_increments_i: quirk(1),
+            jump_uses_vx: quirk(2),
+            vf_reset: quirk(3),
+            clip_sprites: quirk(4),
+            display_wait: quirk(5),
+            // 0 in the movies recorded when fx0a fired on key presses
+            key_wait_on_release: quirk(6),
+            key_wait_beep: quirk(7),
+            collision_counts_rows: quirk(8),
+        }
+    }
+
+    // The original interpreter on the COSMAC VIP
+    pub fn cosmac_vip() -> Quirks {
+        Quirks {
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            jump_uses_vx: false,
+            vf_reset: true,
+            clip_sprites: true,
+            display_wait: true,
+            key_wait_on_release: true,
+            key_wait_beep: true,
+            collision_counts_rows: false,
+        }
+    }
+
+    // CHIP-48 on the HP-48 calculators
+    pub fn chip48() -> Quirks {
+        Quirks {
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            jump_uses_vx: true,
+            vf_reset: false,
+            clip_sprites: true,
+            display_wait: false,
+            key_wait_on_release: true,
+            key_wait_beep: false,
+            collision_counts_rows: false,
+        }
+    }
+
+    // SUPER-CHIP 1.1
+    pub fn superchip() -> Quirks {
+        Quirks {
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            jump_uses_vx: true,
+            vf_reset: false,
+            clip_sprites: true,
+            display_wait: false,
+            key_wait_on_release: true,
+            key_wait_beep: false,
+            collision_counts_rows: true,
+        }
+    }
+
+    // XO-CHIP, as implemented by Octo
+    pub fn xochip() -> Quirks {
+        Quirks {
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            jump_uses_vx: false,
+            vf_reset: false,
+            clip_sprites: false,
+            display_wait: false,
+            key_wait_on_release: true,
+            key_wait_beep: false,
+            collision_counts_rows: false,
+        }
+    }
+}
@@ -0,0 +1,157 @@
+// Rewind: a bounded history of save states, one per frame.
+//
+// Only the newest state is kept in full. Each older state is stored as the difference with the
+// state that came after it: the two states are xor-ed (most bytes of consecutive frames are
+// equal, so the result is mostly zeros) and the zero runs are run-length encoded.
+// Stepping back decodes the newest difference against the newest state, giving the state before
+// it, which becomes the newest state. The oldest differences are dropped when the history is
+// full, they are never needed to rebuild the newer states.
+use crate::chip8::Chip8;
+use std::collections::VecDeque;
+
+// 60 seconds at 60 frames per second
+pub const DEFAULT_CAPACITY: usize = 60 * 60;
+
+pub struct Rewinder {
+    // the maximum number of states kept (including the newest)
+    capacity: usize,
+    // the newest state, in full
+    latest: Option<Vec<u8>>,
+    // deltas.back() rebuilds the state before latest, deltas.front() the oldest state
+    deltas: VecDeque<Vec<u8>>,
+}
+
+impl Rewinder {
+    pub fn new(capacity: usize) -> Rewinder {
+        Rewinder {
+            capacity: capacity.max(1),
+            latest: None,
+            deltas: VecDeque::new(),
+        }
+    }
+
+    // Record the current state of the machine, call once per frame
+    pub fn push(&mut self, chip8: &Chip8) {
+        let state = chip8.save_state();
+        if let Some(latest) = self.latest.take() {
+            self.deltas.push_back(encode_delta(&latest, &state));
+            if self.deltas.len() >= self.capacity {
+                self.deltas.pop_front();
+            }
+        }
+        self.latest = Some(state);
+    }
+
+    // Restore the machine to the state recorded before the newest one, which is forgotten.
+    // Returns false when there is no older state, or it couldn't be restored (the history is
+    // left as it was)
+    pub fn step_back(&mut self, chip8: &mut Chip8) -> bool {
+        let (latest, delta) = match (&self.latest, self.deltas.back()) {
+            (Some(latest), Some(delta)) => (latest, delta),
+            _ => return false,
+        };
+        let previous = decode_delta(latest, delta);
+        if chip8.load_state(&previous).is_err() {
+            return false;
+        }
+        self.deltas.pop_back();
+        self.latest = Some(previous);
+        true
+    }
+
+    // the number of states recorded
+    pub fn len(&self) -> usize {
+        match self.latest {
+            Some(_) => self.deltas.len() + 1,
+            None => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.latest = None;
+        self.deltas.clear();
+    }
+
+    // the bytes used by the history
+    pub fn memory_usage(&self) -> usize {
+        let latest = self.latest.as_ref().map_or(0, Vec::len);
+        latest + self.deltas.iter().map(Vec::len).sum::<usize>()
+    }
+}
+
+// A delta rebuilding old from new:
+//   the length of old (varint)
+//   then until the end: a run of zeros to skip (varint), n (varint), n bytes to xor
+fn encode_delta(old: &[u8], new: &[u8]) -> Vec<u8> {
+    let len = old.len().max(new.len());
+    let diff: Vec<u8> = (0..len)
+        .map(|k| old.get(k).unwrap_or(&0) ^ new.get(k).unwrap_or(&0))
+        .collect();
+
+    let mut delta = Vec::new();
+    write_varint(&mut delta, old.len());
+    let mut pos = 0;
+    while pos < len {
+        let zeros = diff[pos..].iter().take_while(|&&byte| byte == 0).count();
+        if pos + zeros == len {
+            break;
+        }
+        pos += zeros;
+        let literal = diff[pos..].iter().take_while(|&&byte| byte != 0).count();
+        write_varint(&mut delta, zeros);
+        write_varint(&mut delta, literal);
+        delta.extend_from_slice(&diff[pos..pos + literal]);
+        pos += literal;
+    }
+    delta
+}
+
+fn decode_delta(new: &[u8], delta: &[u8]) -> Vec<u8> {
+    let mut input = delta;
+    let old_len = read_varint(&mut input);
+    let mut old = new.to_vec();
+    old.resize(old_len.max(new.len()), 0);
+    let mut pos = 0;
+    while !input.is_empty() {
+        pos += read_varint(&mut input);
+        let literal = read_varint(&mut input);
+        for (byte, diff) in old[pos..pos + literal].iter_mut().zip(&input[..literal]) {
+            *byte ^= diff;
+        }
+        input = &input[literal..];
+        pos += literal;
+    }
+    old.truncate(old_len);
+    old
+}
+
+// 7 bits per byte, the high bit is set on all but the last byte
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7F) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(input: &mut &[u8]) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    while let Some((&byte, rest)) = input.split_first() {
+        *input = rest;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    value
+}
+
+#[cfg(test)]
+#[path = "./rewind_tests.rs"]
+mod rewind_tests;
@@ -0,0 +1,86 @@
+use super::*;
+use crate::quirks::Quirks;
+
+// a program that changes the machine every frame: counts in v0 and draws it
+fn counter() -> Chip8 {
+    let mut instance = Chip8::new(Quirks::default());
+    instance
+        .load_rom_bytes(&[
+            0x70, 0x01, // v0 += 1
+            0xF0, 0x29, // I = font digit v0
+            0xD1, 0x15, // draw it at (v1, v1)
+            0xF0, 0x18, // sound timer = v0
+            0x12, 0x00, // loop
+        ])
+        .unwrap();
+    instance
+}
+
+#[test]
+fn step_back_restores_previous_frames() {
+    let mut instance = counter();
+    let mut rewinder = Rewinder::new(DEFAULT_CAPACITY);
+    let mut states = Vec::new();
+    for _ in 0..50 {
+        instance.run_frame(7).unwrap();
+        rewinder.push(&instance);
+        states.push(instance.save_state());
+    }
+    assert_eq!(rewinder.len(), 50);
+
+    for expected in states.iter().rev().skip(1) {
+        assert!(rewinder.step_back(&mut instance));
+        assert_eq!(&instance.save_state(), expected);
+    }
+    // the oldest state can't be rewound
+    assert!(!rewinder.step_back(&mut instance));
+    assert_eq!(rewinder.len(), 1);
+}
+
+#[test]
+fn history_is_bounded() {
+    let mut instance = counter();
+    let mut rewinder = Rewinder::new(10);
+    for _ in 0..100 {
+        instance.run_frame(7).unwrap();
+        rewinder.push(&instance);
+    }
+    assert_eq!(rewinder.len(), 10);
+    let mut steps = 0;
+    while rewinder.step_back(&mut instance) {
+        steps += 1;
+    }
+    assert_eq!(steps, 9);
+}
+
+#[test]
+fn deltas_are_small() {
+    let mut instance = counter();
+    let mut rewinder = Rewinder::new(DEFAULT_CAPACITY);
+    for _ in 0..DEFAULT_CAPACITY {
+        instance.run_frame(7).unwrap();
+        rewinder.push(&instance);
+    }
+    // a minute of history fits in well under a megabyte
+    let full_states = DEFAULT_CAPACITY * instance.save_state().len();
+    assert!(rewinder.memory_usage() < 1024 * 1024);
+    assert!(rewinder.memory_usage() < full_states / 20);
+}
+
+#[test]
+fn delta_handles_size_changes() {
+    let old = vec![1, 2, 3, 0, 0, 0, 7];
+    let new = vec![1, 2, 4, 0, 9, 0, 7, 8, 9, 10];
+    assert_eq!(decode_delta(&new, &encode_delta(&old, &new)), old);
+    assert_eq!(decode_delta(&old, &encode_delta(&new, &old)), new);
+    assert_eq!(decode_delta(&new, &encode_delta(&new, &new)), new);
+}
+
+#[test]
+fn varint_round_trip() {
+    for &value in &[0, 1, 0x7F, 0x80, 0x3FFF, 0x4000, 65536, usize::MAX >> 1] {
+        let mut out = Vec::new();
+        write_varint(&mut out, value);
+        assert_eq!(read_varint(&mut out.as_slice()), value);
+    }
+}
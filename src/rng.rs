@@ -0,0 +1,45 @@
+// The random number generator behind cxkk: xorshift64*.
+// Its whole state is a single u64, so it can be saved and restored with the rest of the machine.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    // A generator giving the same sequence every time it's created with the same seed
+    pub fn new(seed: u64) -> Rng {
+        // splitmix64, so that close seeds (0, 1, 2...) give unrelated sequences
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Rng::from_state(z ^ (z >> 31))
+    }
+
+    // Restore a generator from a state previously returned by state()
+    pub fn from_state(state: u64) -> Rng {
+        // xorshift gets stuck on 0
+        Rng {
+            state: if state == 0 {
+                0x9E37_79B9_7F4A_7C15
+            } else {
+                state
+            },
+        }
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        // the high bits are the best ones
+        (self.next_u64() >> 56) as u8
+    }
+}
@@ -0,0 +1,177 @@
+// Save states: the whole machine serialized to a versioned binary blob.
+//
+// Layout (little endian):
+//   magic "C8ST", version (u8)
+//   mode (u8), quirks (u16 bitmask, as in the movies)
+//   memory size (u32), memory
+//   v (16 x u8), i (u16), pc (u16), sp (u8), stack (16 x u16)
+//   delay timer (u8), sound timer (u8)
+//   display width (u16), display height (u16), display pixels
+//   keyboard (u16 bitmask), rng state (u64)
+//   rpl flags (8 x u8), exited (u8), planes (u8), audio pattern (16 x u8), pitch (u8), vblank (u8)
+//   key waited for by fx0a (u8, 0xFF when none)
+//
+// The keyboard is saved but not restored: the keys held are the ones held on the host now, not
+// when the state was saved.
+use super::*;
+use crate::binary::{Reader, Truncated, Writer};
+
+const MAGIC: &[u8; 4] = b"C8ST";
+const VERSION: u8 = 4;
+// no key waited for by fx0a
+const NO_KEY: u8 = 0xFF;
+
+impl Chip8 {
+    // Snapshot the whole machine
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = Writer::with_capacity(self.memory.len() + 0x1000);
+        out.header(MAGIC, VERSION);
+
+        out.u8(self.mode.id());
+        out.u16(self.quirks.to_bits());
+
+        out.u32(self.memory.len() as u32);
+        out.bytes(&self.memory);
+        out.bytes(&self.v);
+        out.u16(self.i);
+        out.u16(self.pc);
+        out.u8(self.sp);
+        for &address in self.stack.iter() {
+            out.u16(address);
+        }
+        out.u8(self.delay_timer);
+        out.u8(self.sound_timer);
+
+        out.u16(self.display.width() as u16);
+        out.u16(self.display.height() as u16);
+        out.bytes(self.display.pixels());
+
+        let keys = (0..NUM_KEYS).fold(0u16, |mask, key| mask | (self.keyboard[key] as u16) << key);
+        out.u16(keys);
+        out.u64(self.rng.state());
+
+        out.bytes(&self.rpl);
+        out.bool(self.exited);
+        out.u8(self.planes);
+        out.bytes(&self.audio_pattern);
+        out.u8(self.pitch);
+        out.bool(self.vblank);
+        out.u8(self.key_wait.unwrap_or(NO_KEY));
+        out.into_bytes()
+    }
+
+    // Restore a snapshot taken by save_state, except the keys held. The keys pressed and released
+    // since the last frame are forgotten. Nothing is changed if the snapshot is invalid
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), Chip8Error> {
+        let mut input = Reader::new(data);
+        let version = input
+            .header(MAGIC)?
+            .ok_or_else(|| invalid("not a save state"))?;
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported version {}", version)));
+        }
+
+        let id = input.u8()?;
+        let mode = Mode::from_id(id).ok_or_else(|| invalid(&format!("unknown mode {}", id)))?;
+        let quirks = Quirks::from_bits(input.u16()?);
+
+        let memory_size = input.u32()? as usize;
+        let expected_size = if mode == Mode::XoChip {
+            XO_RAM_SIZE
+        } else {
+            RAM_SIZE
+        };
+        if memory_size != expected_size {
+            return Err(invalid(&format!("wrong memory size {}", memory_size)));
+        }
+        let memory = input.bytes(memory_size)?.to_vec();
+        let mut v = [0; NUM_REGISTERS];
+        v.copy_from_slice(input.bytes(NUM_REGISTERS)?);
+        let i = input.u16()?;
+        let pc = input.u16()?;
+        let sp = input.u8()?;
+        if sp as usize > STACK_SIZE {
+            return Err(invalid(&format!("stack pointer out of range {}", sp)));
+        }
+        let mut stack = [0; STACK_SIZE];
+        for address in stack.iter_mut() {
+            *address = input.u16()?;
+        }
+        let delay_timer = input.u8()?;
+        let sound_timer = input.u8()?;
+
+        let width = input.u16()? as usize;
+        let height = input.u16()? as usize;
+        if (width, height) != (WIDTH, HEIGHT) && (width, height) != (HIRES_WIDTH, HIRES_HEIGHT) {
+            return Err(invalid(&format!("wrong display size {}x{}", width, height)));
+        }
+        let pixels = input.bytes(width * height)?.to_vec();
+
+        // the keyboard
+        input.u16()?;
+        let rng = Rng::from_state(input.u64()?);
+
+        let mut rpl = [0; NUM_RPL_FLAGS];
+        rpl.copy_from_slice(input.bytes(NUM_RPL_FLAGS)?);
+        let exited = bool(&mut input)?;
+        let planes = input.u8()?;
+        let mut audio_pattern = [0; AUDIO_PATTERN_SIZE];
+        audio_pattern.copy_from_slice(input.bytes(AUDIO_PATTERN_SIZE)?);
+        let pitch = input.u8()?;
+        let vblank = bool(&mut input)?;
+        let key_wait = match input.u8()? {
+            NO_KEY => None,
+            key if (key as usize) < NUM_KEYS => Some(key),
+            key => return Err(invalid(&format!("unknown key {}", key))),
+        };
+        if !input.is_empty() {
+            return Err(invalid("unexpected data at the end"));
+        }
+
+        self.mode = mode;
+        self.quirks = quirks;
+        self.memory = memory;
+        self.v = v;
+        self.i = i;
+        self.pc = pc;
+        self.sp = sp;
+        self.stack = stack;
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        self.display = Framebuffer::from_pixels(width, height, pixels);
+        self.pressed_keys = 0;
+        self.released_keys = 0;
+        self.rng = rng;
+        self.rpl = rpl;
+        self.exited = exited;
+        self.planes = planes;
+        self.audio_pattern = audio_pattern;
+        self.pitch = pitch;
+        self.vblank = vblank;
+        self.key_wait = key_wait;
+        self.draw = true;
+        Ok(())
+    }
+}
+
+fn invalid(reason: &str) -> Chip8Error {
+    Chip8Error::InvalidState(reason.to_string())
+}
+
+impl From<Truncated> for Chip8Error {
+    fn from(_: Truncated) -> Chip8Error {
+        invalid("truncated")
+    }
+}
+
+fn bool(input: &mut Reader) -> Result<bool, Chip8Error> {
+    match input.u8()? {
+        0 => Ok(false),
+        1 => Ok(true),
+        other => Err(invalid(&format!("invalid boolean {}", other))),
+    }
+}
+
+#[cfg(test)]
+#[path = "./savestate_tests.rs"]
+mod savestate_tests;
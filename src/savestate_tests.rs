@@ -0,0 +1,114 @@
+use crate::chip8::{Chip8, Mode};
+use crate::error::Chip8Error;
+use crate::quirks::Quirks;
+
+// a machine in the middle of something
+fn busy_machine() -> Chip8 {
+    let mut instance = Chip8::new(Quirks::superchip());
+    instance.set_mode(Mode::SuperChip);
+    instance
+        .load_rom_bytes(&[
+            0x00, 0xFF, // hires
+            0x60, 0x05, // v0 = 5
+            0xA0, 0x00, // I = 0 (the font)
+            0xD0, 0x05, // draw 0 at (5, 5)
+            0x22, 0x0C, // call 0x20c
+            0x12, 0x0A, // loop forever
+            0xC1, 0xFF, // v1 = random
+            0xF0, 0x15, // delay timer = v0
+            0x12, 0x10, // loop forever
+        ])
+        .unwrap();
+    instance.run_frame(10).unwrap();
+    instance.set_key(0xA, true);
+    instance
+}
+
+#[test]
+fn round_trip() {
+    let instance = busy_machine();
+    let state = instance.save_state();
+
+    // with the same key held, the keyboard isn't restored
+    let mut restored = Chip8::new(Quirks::default());
+    restored.key_down(0xA);
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.save_state(), state);
+    assert_eq!(restored.mode(), Mode::SuperChip);
+    assert_eq!(restored.quirks(), Quirks::superchip());
+    assert_eq!(restored.framebuffer(), instance.framebuffer());
+    assert_eq!(restored.sp(), 1);
+    assert_eq!(restored.pc(), 0x210);
+}
+
+#[test]
+fn keys_held_are_not_restored() {
+    let mut instance = busy_machine();
+    let state = instance.save_state();
+    instance.key_up(0xA);
+    instance.key_down(0x3);
+
+    instance.load_state(&state).unwrap();
+    assert!(!instance.keys()[0xA]);
+    // held through the restore
+    assert!(instance.keys()[0x3]);
+    assert_eq!(instance.key_edges(), (0, 0));
+}
+
+#[test]
+fn round_trip_key_wait() {
+    let mut instance = Chip8::new(Quirks::cosmac_vip());
+    instance.load_rom_bytes(&[0xF0, 0x0A]).unwrap();
+    instance.set_key(0x4, true);
+    instance.step().unwrap();
+    let state = instance.save_state();
+
+    // the key released after the restore is the one fx0a was waiting for
+    let mut restored = Chip8::new(Quirks::default());
+    restored.set_key(0x4, true);
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.quirks(), Quirks::cosmac_vip());
+    restored.step().unwrap();
+    assert_eq!(restored.pc(), 0x200);
+    restored.set_key(0x4, false);
+    restored.step().unwrap();
+    assert_eq!(restored.pc(), 0x202);
+    assert_eq!(restored.v()[0], 0x4);
+}
+
+#[test]
+fn restores_rng() {
+    let mut instance = busy_machine();
+    let state = instance.save_state();
+    let first: Vec<u8> = (0..8).map(|_| instance.rng.next_u8()).collect();
+
+    instance.load_state(&state).unwrap();
+    let second: Vec<u8> = (0..8).map(|_| instance.rng.next_u8()).collect();
+    assert_eq!(first, second);
+}
+
+#[test]
+fn rejects_bad_version() {
+    let mut state = busy_machine().save_state();
+    state[4] = 0xFF;
+    let mut instance = Chip8::new(Quirks::default());
+    assert!(matches!(
+        instance.load_state(&state),
+        Err(Chip8Error::InvalidState(_))
+    ));
+}
+
+#[test]
+fn rejects_bad_size() {
+    let state = busy_machine().save_state();
+    let mut instance = Chip8::new(Quirks::default());
+    let before = instance.save_state();
+
+    assert!(instance.load_state(&state[..state.len() - 1]).is_err());
+    let mut longer = state.clone();
+    longer.push(0);
+    assert!(instance.load_state(&longer).is_err());
+    assert!(instance.load_state(b"not a save state").is_err());
+    // nothing changed
+    assert_eq!(instance.save_state(), before);
+}
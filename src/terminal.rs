@@ -0,0 +1,293 @@
+use crate::frontend::Frontend;
+use chip8_emulator::config::{Binding, Hotkey};
+use chip8_emulator::persistence::Persistence;
+use chip8_emulator::tui::{self, Glyphs};
+use chip8_emulator::{Chip8, Palette};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::mem;
+
+// Terminals don't report releasing a key: a key is held for this many frames after the last
+// character it typed. Holding it down types the character once, then again after the repeat
+// delay of the keyboard (250 to 660ms, X defaults to 660ms) and every 30ms or so after that: the
+// hold outlasts the delay, so a key held down isn't released before its first repeat. A tap
+// stays held for as long, 2/3 of a second
+const KEY_HOLD: u32 = 40;
+// the register panel is refreshed this often, in frames, when the screen doesn't change
+const PANEL_FRAMES: u32 = 6;
+// between the screen and the panel
+const GAP: &str = "  ";
+
+// The terminal frontend: the screen as Unicode characters and the registers next to it, in the
+// alternate screen of the terminal, the keys read from stdin in raw mode and the sound as the
+// bell. The terminal is restored when it's dropped
+pub struct Terminal {
+    glyphs: Glyphs,
+    palette: Palette,
+    persistence: Persistence,
+    // the keys of the CHIP-8 keypad and the hotkeys, by name
+    bindings: HashMap<String, Binding>,
+    // the keys held, by name, and the frames left until they're released
+    held: HashMap<String, u32>,
+    // the terminal settings before raw mode
+    original: libc::termios,
+    // the size of the terminal (columns, rows) on the last draw
+    size: (u16, u16),
+    // the lines on the terminal
+    shown: Vec<String>,
+    // the last message, under the screen
+    status: String,
+    // every message, printed again once the terminal is restored: (error, text)
+    log: Vec<(bool, String)>,
+    // frames since the last draw
+    frames: u32,
+    // the status line changed, or the palette
+    dirty: bool,
+    // the bell rang for this sound
+    beeping: bool,
+}
+
+impl Terminal {
+    pub fn open(
+        glyphs: Glyphs,
+        palette: Palette,
+        persistence: Persistence,
+        bindings: &[(String, Binding)],
+    ) -> Result<Terminal, String> {
+        let original = unsafe {
+            let mut original = mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                return Err(format!(
+                    "stdin isn't a terminal: {}",
+                    io::Error::last_os_error()
+                ));
+            }
+            let mut raw = original;
+            libc::cfmakeraw(&mut raw);
+            // reads return what was typed, without waiting
+            raw.c_cc[libc::VMIN] = 0;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return Err(format!(
+                    "Couldn't set raw mode: {}",
+                    io::Error::last_os_error()
+                ));
+            }
+            original
+        };
+        let terminal = Terminal {
+            glyphs,
+            palette,
+            persistence,
+            bindings: bindings.iter().cloned().collect(),
+            held: HashMap::new(),
+            original,
+            size: (0, 0),
+            shown: Vec::new(),
+            status: String::new(),
+            log: Vec::new(),
+            frames: 0,
+            dirty: true,
+            beeping: false,
+        };
+        // the alternate screen, without the cursor
+        terminal.write("\x1b[?1049h\x1b[?25l\x1b[2J")?;
+        Ok(terminal)
+    }
+
+    fn write(&self, text: &str) -> Result<(), String> {
+        let mut stdout = io::stdout();
+        stdout
+            .write_all(text.as_bytes())
+            .and_then(|()| stdout.flush())
+            .map_err(|e| e.to_string())
+    }
+
+    // what was typed since the last call
+    fn read(&self) -> Vec<u8> {
+        let mut input = Vec::new();
+        let mut buffer = [0u8; 64];
+        loop {
+            let read = unsafe {
+                libc::read(
+                    libc::STDIN_FILENO,
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                )
+            };
+            if read <= 0 {
+                break;
+            }
+            input.extend_from_slice(&buffer[..read as usize]);
+        }
+        input
+    }
+
+    // write the lines that changed, all of them when the terminal was resized
+    fn show(&mut self, lines: Vec<String>) -> Result<(), String> {
+        let mut output = String::new();
+        let size = window_size();
+        if size != self.size {
+            self.size = size;
+            self.shown.clear();
+            output.push_str("\x1b[2J");
+        }
+        for (row, line) in lines.iter().enumerate() {
+            if self.shown.get(row) != Some(line) {
+                let _ = write!(output, "\x1b[{};1H{}\x1b[K", row + 1, line);
+            }
+        }
+        // a lower resolution takes fewer lines
+        if lines.len() < self.shown.len() {
+            let _ = write!(output, "\x1b[{};1H\x1b[J", lines.len() + 1);
+        }
+        self.shown = lines;
+        self.write(&output)
+    }
+
+    fn release(&self, keypad: Option<&mut Chip8>, name: &str) {
+        if let (Some(&Binding::Key(key)), Some(chip8)) = (self.bindings.get(name), keypad) {
+            chip8.key_up(key as usize);
+        }
+    }
+}
+
+impl Frontend for Terminal {
+    fn handle_input(&mut self, mut keypad: Option<&mut Chip8>) -> Vec<Hotkey> {
+        self.frames += 1;
+        let mut released = Vec::new();
+        for (name, frames) in self.held.iter_mut() {
+            *frames -= 1;
+            if *frames == 0 {
+                released.push(name.clone());
+            }
+        }
+        for name in released {
+            self.held.remove(&name);
+            self.release(keypad.as_deref_mut(), &name);
+        }
+
+        let mut hotkeys = Vec::new();
+        for name in tui::decode_keys(&self.read()) {
+            if name == tui::INTERRUPT {
+                hotkeys.push(Hotkey::Quit);
+                continue;
+            }
+            // a repeat of a key held
+            if self.held.insert(name.clone(), KEY_HOLD).is_some() {
+                continue;
+            }
+            match self.bindings.get(&name) {
+                Some(&Binding::Key(key)) => {
+                    if let Some(chip8) = keypad.as_deref_mut() {
+                        chip8.key_down(key as usize);
+                    }
+                }
+                Some(Binding::Hotkey(Hotkey::Rewind)) => {}
+                Some(&Binding::Hotkey(hotkey)) => hotkeys.push(hotkey),
+                None => {}
+            }
+        }
+        let rewind = Binding::Hotkey(Hotkey::Rewind);
+        let rewinding = self
+            .held
+            .keys()
+            .any(|name| self.bindings.get(name) == Some(&rewind));
+        if rewinding {
+            hotkeys.push(Hotkey::Rewind);
+        }
+        hotkeys
+    }
+
+    // the screen with the panel on its right, then the status line
+    fn draw(&mut self, chip8: &Chip8) -> Result<(), String> {
+        let framebuffer = chip8.framebuffer();
+        let (width, height) = (framebuffer.width(), framebuffer.height());
+        let image = self.persistence.render(framebuffer, &self.palette);
+        let screen = tui::render(image, width, height, self.palette.colors[0], self.glyphs);
+        let columns = match self.glyphs {
+            Glyphs::Half => width,
+            Glyphs::Braille => width.div_ceil(2),
+        };
+        let panel = tui::panel(chip8);
+        let mut lines = Vec::new();
+        for row in 0..screen.len().max(panel.len()) {
+            let left = screen
+                .get(row)
+                .cloned()
+                .unwrap_or_else(|| " ".repeat(columns));
+            let right = panel.get(row).map_or("", String::as_str);
+            lines.push(format!("{}{}{}", left, GAP, right));
+        }
+        lines.push(String::new());
+        lines.push(self.status.clone());
+        self.frames = 0;
+        self.dirty = false;
+        self.show(lines)
+    }
+
+    // the panel is refreshed while the screen doesn't change
+    fn needs_draw(&mut self) -> bool {
+        self.dirty
+            || self.persistence.fading()
+            || self.frames >= PANEL_FRAMES
+            || window_size() != self.size
+    }
+
+    // the bell rings once per sound
+    fn beep(&mut self, _chip8: &Chip8) {
+        if !self.beeping {
+            self.beeping = true;
+            let _ = self.write("\x07");
+        }
+    }
+
+    fn stop_beep(&mut self) {
+        self.beeping = false;
+    }
+
+    fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.dirty = true;
+    }
+
+    fn message(&mut self, text: &str) {
+        self.status = text.to_string();
+        self.log.push((false, text.to_string()));
+        self.dirty = true;
+    }
+
+    fn error(&mut self, text: &str) {
+        self.status = text.to_string();
+        self.log.push((true, text.to_string()));
+        self.dirty = true;
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.write("\x1b[0m\x1b[?25h\x1b[?1049l");
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+        for (error, text) in &self.log {
+            if *error {
+                eprintln!("{}", text);
+            } else {
+                println!("{}", text);
+            }
+        }
+    }
+}
+
+// (columns, rows), (0, 0) when stdout isn't a terminal
+fn window_size() -> (u16, u16) {
+    unsafe {
+        let mut size: libc::winsize = mem::zeroed();
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) != 0 {
+            return (0, 0);
+        }
+        (size.ws_col, size.ws_row)
+    }
+}
@@ -0,0 +1,235 @@
+// Execution traces: every instruction executed, with the registers it changed.
+//
+// The machine is deterministic once the ROM, the seed, the mode, the quirks and the input are
+// fixed, so two builds of the emulator running the same program write the same trace until one
+// of them misbehaves: diffing the traces points at the first instruction they disagree on.
+// Nothing time or machine dependent is written.
+//
+// Text format, one instruction per line: pc, opcode, mnemonic, the new values of the registers
+// it changed (hexadecimal):
+//   0x0206 8124 ADD V1, V2               V1=0A VF=01
+// Binary format, for long runs (little endian):
+//   magic "C8TR", version (u8)
+//   for each instruction: pc (u16), opcode (u16), operand (u16, only after f000),
+//   the registers changed (u32 bitmask, bit n is REGISTERS[n]), their new values (u16 each)
+use crate::binary::{Reader, Truncated, Writer};
+use crate::chip8::{Chip8, NUM_REGISTERS};
+use crate::disasm::{self, Syntax};
+use crate::error::Chip8Error;
+use crate::instruction::{decode, Instruction};
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+
+const MAGIC: &[u8; 4] = b"C8TR";
+const VERSION: u8 = 1;
+// the registers traced: V0-VF, I, SP, DT, ST
+pub const REGISTERS: [&str; NUM_TRACED] = [
+    "V0", "V1", "V2", "V3", "V4", "V5", "V6", "V7", "V8", "V9", "VA", "VB", "VC", "VD", "VE", "VF",
+    "I", "SP", "DT", "ST",
+];
+const NUM_TRACED: usize = NUM_REGISTERS + 4;
+// width of the mnemonic column
+const MNEMONIC_WIDTH: usize = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Text,
+    Binary,
+}
+
+// An executed instruction
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    // where the instruction was
+    pub pc: u16,
+    pub opcode: u16,
+    // the word after f000, 0 for the other instructions
+    pub operand: u16,
+    // (index in REGISTERS, new value) of the registers changed, in REGISTERS order
+    pub changes: Vec<(usize, u16)>,
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mnemonic = disasm::format(decode(self.opcode), self.operand, Syntax::Cowgod, &|addr| {
+            format!("{:#05X}", addr)
+        });
+        let changes: Vec<String> = self
+            .changes
+            .iter()
+            .map(|&(register, value)| match REGISTERS[register] {
+                "I" => format!("I={:04X}", value),
+                name => format!("{}={:02X}", name, value),
+            })
+            .collect();
+        let line = format!(
+            "{:#06X} {:04X} {:width$} {}",
+            self.pc,
+            self.opcode,
+            mnemonic,
+            changes.join(" "),
+            width = MNEMONIC_WIDTH
+        );
+        write!(f, "{}", line.trim_end())
+    }
+}
+
+// Writes the trace of the instructions run through it
+pub struct Tracer<W: Write> {
+    out: W,
+    format: Format,
+    // only the instructions at these addresses are written
+    range: Option<RangeInclusive<u16>>,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(
+        mut out: W,
+        format: Format,
+        range: Option<RangeInclusive<u16>>,
+    ) -> io::Result<Tracer<W>> {
+        if format == Format::Binary {
+            let mut header = Writer::with_capacity(MAGIC.len() + 1);
+            header.header(MAGIC, VERSION);
+            out.write_all(&header.into_bytes())?;
+        }
+        Ok(Tracer { out, format, range })
+    }
+
+    // Execute one instruction and trace it. Write errors are returned as Chip8Error::Io
+    pub fn step(&mut self, chip8: &mut Chip8) -> Result<(), Chip8Error> {
+        let pc = chip8.pc();
+        let memory = chip8.memory();
+        let word = |addr: u16| {
+            let addr = addr as usize;
+            match (memory.get(addr), memory.get(addr + 1)) {
+                (Some(&high), Some(&low)) => u16::from_be_bytes([high, low]),
+                _ => 0,
+            }
+        };
+        let opcode = word(pc);
+        let operand = match decode(opcode) {
+            Instruction::LoadILong => word(pc.wrapping_add(2)),
+            _ => 0,
+        };
+        let before = registers(chip8);
+        chip8.step()?;
+        if self
+            .range
+            .as_ref()
+            .is_some_and(|range| !range.contains(&pc))
+        {
+            return Ok(());
+        }
+
+        let after = registers(chip8);
+        let changes = (0..NUM_TRACED)
+            .filter(|&register| before[register] != after[register])
+            .map(|register| (register, after[register]))
+            .collect();
+        let record = Record {
+            pc,
+            opcode,
+            operand,
+            changes,
+        };
+        match self.format {
+            Format::Text => writeln!(self.out, "{}", record)?,
+            Format::Binary => self.out.write_all(&to_bytes(&record))?,
+        }
+        Ok(())
+    }
+
+    // Chip8::run_frame, traced
+    pub fn run_frame(&mut self, chip8: &mut Chip8, ipf: usize) -> Result<(), Chip8Error> {
+        for _ in 0..ipf {
+            if chip8.exited() {
+                break;
+            }
+            self.step(chip8)?;
+        }
+        chip8.handle_timers();
+        Ok(())
+    }
+
+    // flush the trace and give back the writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+fn registers(chip8: &Chip8) -> [u16; NUM_TRACED] {
+    let mut registers = [0; NUM_TRACED];
+    for (register, &value) in registers.iter_mut().zip(chip8.v().iter()) {
+        *register = value as u16;
+    }
+    registers[NUM_REGISTERS] = chip8.i();
+    registers[NUM_REGISTERS + 1] = chip8.sp() as u16;
+    registers[NUM_REGISTERS + 2] = chip8.delay_timer() as u16;
+    registers[NUM_REGISTERS + 3] = chip8.sound_timer() as u16;
+    registers
+}
+
+fn to_bytes(record: &Record) -> Vec<u8> {
+    let mut out = Writer::with_capacity(10 + record.changes.len() * 2);
+    out.u16(record.pc);
+    out.u16(record.opcode);
+    if decode(record.opcode) == Instruction::LoadILong {
+        out.u16(record.operand);
+    }
+    let mask = record
+        .changes
+        .iter()
+        .fold(0u32, |mask, &(register, _)| mask | 1 << register);
+    out.u32(mask);
+    for &(_, value) in record.changes.iter() {
+        out.u16(value);
+    }
+    out.into_bytes()
+}
+
+// Read a binary trace
+pub fn from_bytes(data: &[u8]) -> Result<Vec<Record>, String> {
+    let mut input = Reader::new(data);
+    let version = input.header(MAGIC)?.ok_or("not a trace")?;
+    if version != VERSION {
+        return Err(format!("unsupported version {}", version));
+    }
+
+    let mut records = Vec::new();
+    while !input.is_empty() {
+        let pc = input.u16()?;
+        let opcode = input.u16()?;
+        let operand = match decode(opcode) {
+            Instruction::LoadILong => input.u16()?,
+            _ => 0,
+        };
+        let mask = input.u32()?;
+        if mask >> NUM_TRACED != 0 {
+            return Err(format!("unknown registers changed at {:#06X}", pc));
+        }
+        let mut changes = Vec::new();
+        for register in (0..NUM_TRACED).filter(|register| mask & 1 << register != 0) {
+            changes.push((register, input.u16()?));
+        }
+        records.push(Record {
+            pc,
+            opcode,
+            operand,
+            changes,
+        });
+    }
+    Ok(records)
+}
+
+impl From<Truncated> for String {
+    fn from(_: Truncated) -> String {
+        "unexpected end of the trace".to_string()
+    }
+}
+
+#[cfg(test)]
+#[path = "./trace_tests.rs"]
+mod trace_tests;
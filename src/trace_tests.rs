@@ -0,0 +1,71 @@
+use super::*;
+use crate::quirks::Quirks;
+
+const ROM: [u8; 12] = [
+    0x61, 0x05, // 0x200: v1 = 5
+    0x62, 0x05, // 0x202: v2 = 5
+    0x81, 0x24, // 0x204: v1 += v2
+    0xA3, 0x00, // 0x206: I = 0x300
+    0x22, 0x0A, // 0x208: call 0x20A
+    0x12, 0x0A, // 0x20A: loop
+];
+
+fn trace(format: Format, range: Option<RangeInclusive<u16>>, steps: usize) -> Vec<u8> {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.load_rom_bytes(&ROM).unwrap();
+    let mut tracer = Tracer::new(Vec::new(), format, range).unwrap();
+    for _ in 0..steps {
+        tracer.step(&mut instance).unwrap();
+    }
+    tracer.finish().unwrap()
+}
+
+#[test]
+fn text_trace() {
+    let text = String::from_utf8(trace(Format::Text, None, 7)).unwrap();
+    assert_eq!(
+        text,
+        "0x0200 6105 LD V1, 0x05              V1=05
+0x0202 6205 LD V2, 0x05              V2=05
+0x0204 8124 ADD V1, V2               V1=0A
+0x0206 A300 LD I, 0x300              I=0300
+0x0208 220A CALL 0x20A               SP=01
+0x020A 120A JP 0x20A
+0x020A 120A JP 0x20A
+"
+    );
+}
+
+#[test]
+fn text_trace_filtered_by_address() {
+    let text = String::from_utf8(trace(Format::Text, Some(0x204..=0x208), 7)).unwrap();
+    let pcs: Vec<&str> = text.lines().map(|line| &line[..6]).collect();
+    assert_eq!(pcs, ["0x0204", "0x0206", "0x0208"]);
+}
+
+#[test]
+fn binary_trace_matches_text_trace() {
+    let records = from_bytes(&trace(Format::Binary, None, 7)).unwrap();
+    assert_eq!(records.len(), 7);
+    assert_eq!(records[2].changes, [(1, 0x0A)]);
+    let text: String = records
+        .iter()
+        .map(|record| format!("{}\n", record))
+        .collect();
+    assert_eq!(text.as_bytes(), &trace(Format::Text, None, 7)[..]);
+}
+
+#[test]
+fn binary_trace_long_load() {
+    let mut instance = Chip8::new(Quirks::xochip());
+    instance.set_mode(crate::chip8::Mode::XoChip);
+    instance.load_rom_bytes(&[0xF0, 0x00, 0x12, 0x34]).unwrap();
+    let mut tracer = Tracer::new(Vec::new(), Format::Binary, None).unwrap();
+    tracer.step(&mut instance).unwrap();
+    let records = from_bytes(&tracer.finish().unwrap()).unwrap();
+    assert_eq!(
+        records[0].to_string(),
+        "0x0200 F000 LD I, long 0x1234        I=1234"
+    );
+    assert!(from_bytes(b"C8TR\x01\x00\x02").is_err());
+}
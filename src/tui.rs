@@ -0,0 +1,297 @@
+// The parts of the terminal frontend that don't touch the terminal: the screen as lines of
+// Unicode characters in 24 bit ANSI colors, the register panel, and the keys in what stdin
+// receives in raw mode.
+//
+// Glyphs:
+//     half     a character per 1x2 pixels: the upper half block, in the color of the top pixel
+//              over the color of the bottom one. 64x32 takes 64x16 characters
+//     braille  a character per 2x4 pixels, a dot per lit pixel, in a single color per character.
+//              64x32 takes 32x8 characters
+//
+// The keys are named as in the config file (config::key_name), the same bindings work in the
+// window and in the terminal. Terminals can't tell the keypad from the other digits, and don't
+// report releasing a key.
+use crate::chip8::Chip8;
+use crate::disasm;
+use crate::palette::Rgb;
+use std::fmt::{self, Write};
+
+// what Ctrl+C sends, it quits the emulator: the terminal doesn't interrupt it in raw mode
+pub const INTERRUPT: &str = "Ctrl+C";
+
+const UPPER_HALF: char = '\u{2580}';
+const BRAILLE: u32 = 0x2800;
+// the dot of each pixel of a braille character, by (x, y)
+const BRAILLE_DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Glyphs {
+    Half,
+    Braille,
+}
+
+impl Glyphs {
+    pub fn parse(name: &str) -> Option<Glyphs> {
+        match name {
+            "half" => Some(Glyphs::Half),
+            "braille" => Some(Glyphs::Braille),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Glyphs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Glyphs::Half => write!(f, "half"),
+            Glyphs::Braille => write!(f, "braille"),
+        }
+    }
+}
+
+// The text of an image of width x height colors, row by row. Pixels in the background color are
+// off for braille. Every line ends by resetting the colors
+pub fn render(
+    image: &[Rgb],
+    width: usize,
+    height: usize,
+    background: Rgb,
+    glyphs: Glyphs,
+) -> Vec<String> {
+    // an odd height ends with a half line
+    let pixel = |x: usize, y: usize| {
+        if y < height {
+            image[y * width + x]
+        } else {
+            background
+        }
+    };
+    let mut lines = Vec::new();
+    match glyphs {
+        Glyphs::Half => {
+            for y in (0..height).step_by(2) {
+                let mut line = Colors::new();
+                for x in 0..width {
+                    line.push(UPPER_HALF, pixel(x, y), pixel(x, y + 1));
+                }
+                lines.push(line.finish());
+            }
+        }
+        Glyphs::Braille => {
+            for y in (0..height).step_by(4) {
+                let mut line = Colors::new();
+                for x in (0..width).step_by(2) {
+                    let mut dots = 0;
+                    // the color of the first lit pixel
+                    let mut color = None;
+                    for (dx, column) in BRAILLE_DOTS.iter().enumerate() {
+                        for (dy, &dot) in column.iter().enumerate() {
+                            let (x, y) = (x + dx, y + dy);
+                            if x < width && pixel(x, y) != background {
+                                dots |= dot;
+                                color = color.or(Some(pixel(x, y)));
+                            }
+                        }
+                    }
+                    let glyph = std::char::from_u32(BRAILLE + dots).unwrap_or(' ');
+                    // a blank keeps the color of the last character, writing none
+                    let color = color.or_else(|| line.foreground());
+                    line.push(glyph, color.unwrap_or(background), background);
+                }
+                lines.push(line.finish());
+            }
+        }
+    }
+    lines
+}
+
+// a line of characters, the colors only written when they change
+struct Colors {
+    text: String,
+    colors: Option<(Rgb, Rgb)>,
+}
+
+impl Colors {
+    fn new() -> Colors {
+        Colors {
+            text: String::new(),
+            colors: None,
+        }
+    }
+
+    fn push(&mut self, glyph: char, foreground: Rgb, background: Rgb) {
+        let last = self.colors;
+        if last.map(|colors| colors.0) != Some(foreground) {
+            let [r, g, b] = foreground;
+            let _ = write!(self.text, "\x1b[38;2;{};{};{}m", r, g, b);
+        }
+        if last.map(|colors| colors.1) != Some(background) {
+            let [r, g, b] = background;
+            let _ = write!(self.text, "\x1b[48;2;{};{};{}m", r, g, b);
+        }
+        self.colors = Some((foreground, background));
+        self.text.push(glyph);
+    }
+
+    fn foreground(&self) -> Option<Rgb> {
+        self.colors.map(|colors| colors.0)
+    }
+
+    fn finish(mut self) -> String {
+        self.text.push_str("\x1b[0m");
+        self.text
+    }
+}
+
+// The registers, the timers and the next instruction, a line each
+pub fn panel(chip8: &Chip8) -> Vec<String> {
+    let mut lines = vec![
+        format!("PC {:04X}   I {:04X}", chip8.pc(), chip8.i()),
+        format!(
+            "SP {:<2}  DT {:02X}  ST {:02X}",
+            chip8.sp(),
+            chip8.delay_timer(),
+            chip8.sound_timer()
+        ),
+    ];
+    for (row, registers) in chip8.v().chunks(4).enumerate() {
+        let mut line = String::new();
+        for (column, value) in registers.iter().enumerate() {
+            let separator = if column == 0 { "" } else { " " };
+            let _ = write!(line, "{}V{:X} {:02X}", separator, row * 4 + column, value);
+        }
+        lines.push(line);
+    }
+    let (instruction, _) = disasm::disassemble(chip8.memory(), chip8.pc() as usize, chip8.mode());
+    lines.push(format!("{:04X} {}", chip8.pc(), instruction));
+    lines
+}
+
+// The keys in the bytes read from stdin, by name. A lone escape is the Escape key
+pub fn decode_keys(input: &[u8]) -> Vec<String> {
+    let mut keys = Vec::new();
+    let mut rest = input;
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        let key = match byte {
+            0x1B if rest.is_empty() => Some("Escape".to_string()),
+            0x1B => {
+                let (key, length) = escape_sequence(rest);
+                rest = &rest[length..];
+                key.map(str::to_string)
+            }
+            0x03 => Some(INTERRUPT.to_string()),
+            0x7F | 0x08 => Some("Backspace".to_string()),
+            b'\t' => Some("Tab".to_string()),
+            b'\r' | b'\n' => Some("Return".to_string()),
+            b' ' => Some("Space".to_string()),
+            _ if byte.is_ascii_alphanumeric() => {
+                Some((byte as char).to_ascii_uppercase().to_string())
+            }
+            _ => character(byte).map(str::to_string),
+        };
+        keys.extend(key);
+    }
+    keys
+}
+
+// the punctuation keys, by the character they type
+fn character(byte: u8) -> Option<&'static str> {
+    let name = match byte {
+        b'-' => "Minus",
+        b'=' => "Equals",
+        b',' => "Comma",
+        b'.' => "Period",
+        b';' => "Semicolon",
+        b'\'' => "Quote",
+        b'/' => "Slash",
+        b'\\' => "Backslash",
+        b'[' => "LeftBracket",
+        b']' => "RightBracket",
+        b'`' => "Backquote",
+        _ => return None,
+    };
+    Some(name)
+}
+
+// The key of the sequence after an escape, and the length of the sequence. Modifiers are ignored:
+// ESC [ 1 ; 5 A is Up
+fn escape_sequence(input: &[u8]) -> (Option<&'static str>, usize) {
+    match input.first() {
+        // SS3: F1 - F4, and the arrows, Home and End in application mode
+        Some(b'O') => {
+            let key = input.get(1).and_then(|&byte| final_key(byte));
+            (key, input.len().min(2))
+        }
+        // CSI: parameters, then a final byte
+        Some(b'[') => {
+            let end = input[1..]
+                .iter()
+                .position(|byte| (0x40..=0x7E).contains(byte))
+                .map(|position| position + 1);
+            let end = match end {
+                Some(end) => end,
+                // cut short, dropped
+                None => return (None, input.len()),
+            };
+            let parameters = std::str::from_utf8(&input[1..end]).unwrap_or("");
+            let key = match input[end] {
+                b'~' => {
+                    let number = parameters.split(';').next().unwrap_or("");
+                    tilde_key(number.parse().unwrap_or(0))
+                }
+                byte => final_key(byte),
+            };
+            (key, end + 1)
+        }
+        // Alt and a key: the key
+        _ => (None, 0),
+    }
+}
+
+fn final_key(byte: u8) -> Option<&'static str> {
+    let name = match byte {
+        b'A' => "Up",
+        b'B' => "Down",
+        b'C' => "Right",
+        b'D' => "Left",
+        b'H' => "Home",
+        b'F' => "End",
+        b'P' => "F1",
+        b'Q' => "F2",
+        b'R' => "F3",
+        b'S' => "F4",
+        _ => return None,
+    };
+    Some(name)
+}
+
+// ESC [ n ~
+fn tilde_key(number: u8) -> Option<&'static str> {
+    let name = match number {
+        1 | 7 => "Home",
+        2 => "Insert",
+        3 => "Delete",
+        4 | 8 => "End",
+        5 => "PageUp",
+        6 => "PageDown",
+        11 => "F1",
+        12 => "F2",
+        13 => "F3",
+        14 => "F4",
+        15 => "F5",
+        17 => "F6",
+        18 => "F7",
+        19 => "F8",
+        20 => "F9",
+        21 => "F10",
+        23 => "F11",
+        24 => "F12",
+        _ => return None,
+    };
+    Some(name)
+}
+
+#[cfg(test)]
+#[path = "./tui_tests.rs"]
+mod tui_tests;
@@ -0,0 +1,70 @@
+use super::*;
+use crate::quirks::Quirks;
+
+const OFF: Rgb = [0, 0, 0];
+const ON: Rgb = [255, 255, 255];
+
+#[test]
+fn render_half_blocks() {
+    // 2x3: the last line is half of the background
+    let image = [ON, OFF, OFF, ON, ON, ON];
+    let lines = render(&image, 2, 3, OFF, Glyphs::Half);
+    assert_eq!(
+        lines,
+        [
+            "\x1b[38;2;255;255;255m\x1b[48;2;0;0;0m\u{2580}\
+             \x1b[38;2;0;0;0m\x1b[48;2;255;255;255m\u{2580}\x1b[0m",
+            "\x1b[38;2;255;255;255m\x1b[48;2;0;0;0m\u{2580}\u{2580}\x1b[0m",
+        ]
+    );
+}
+
+#[test]
+fn render_braille() {
+    // 3x4: the left column and the top pixel of the second one fill the first cell, the third
+    // column is off: its cell is blank
+    let mut image = [OFF; 12];
+    for &i in [0, 1, 3, 6, 9].iter() {
+        image[i] = ON;
+    }
+    let lines = render(&image, 3, 4, OFF, Glyphs::Braille);
+    let colors = "\x1b[38;2;255;255;255m\x1b[48;2;0;0;0m";
+    assert_eq!(lines, [format!("{}\u{284F}\u{2800}\x1b[0m", colors)]);
+}
+
+#[test]
+fn register_panel() {
+    let mut instance = Chip8::new(Quirks::default());
+    instance.load_rom_bytes(&[0x6A, 0x42]).unwrap();
+    instance.set_v(0xA, 0x42);
+    let lines = panel(&instance);
+    assert_eq!(lines[0], "PC 0200   I 0000");
+    assert_eq!(lines[1], "SP 0   DT 00  ST 00");
+    assert_eq!(lines[4], "V8 00 V9 00 VA 42 VB 00");
+    assert_eq!(lines[6], "0200 LD VA, 0x42");
+}
+
+#[test]
+fn decode_characters() {
+    assert_eq!(
+        decode_keys(b"qW5 -\x7f\r"),
+        ["Q", "W", "5", "Space", "Minus", "Backspace", "Return"]
+    );
+    // shifted symbols aren't keys
+    assert!(decode_keys(b"!@").is_empty());
+    assert_eq!(decode_keys(b"\x03"), [INTERRUPT]);
+}
+
+#[test]
+fn decode_escape_sequences() {
+    assert_eq!(decode_keys(b"\x1b"), ["Escape"]);
+    assert_eq!(decode_keys(b"\x1b[A\x1b[1;5D\x1bOP"), ["Up", "Left", "F1"]);
+    assert_eq!(
+        decode_keys(b"\x1b[15~\x1b[24~\x1b[5~\x1b[3;2~"),
+        ["F5", "F12", "PageUp", "Delete"]
+    );
+    // unknown and cut short sequences are dropped
+    assert_eq!(decode_keys(b"\x1b[99~q\x1b[12"), ["Q"]);
+    // Alt+key is the key
+    assert_eq!(decode_keys(b"\x1bx"), ["X"]);
+}
@@ -0,0 +1,69 @@
+// Where the screen goes in a window of any size: the largest area with the aspect ratio of the
+// screen, centered. The rest of the window is the border (letterboxing).
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scaling {
+    // whole multiples of the screen size, every CHIP-8 pixel the same size
+    Integer,
+    // as large as the window allows, the pixels may differ by one
+    Fit,
+}
+
+impl Scaling {
+    pub fn parse(name: &str) -> Option<Scaling> {
+        match name {
+            "integer" => Some(Scaling::Integer),
+            "fit" => Some(Scaling::Fit),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Scaling {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Scaling::Integer => write!(f, "integer"),
+            Scaling::Fit => write!(f, "fit"),
+        }
+    }
+}
+
+// An area of the window, in window pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// The area of a window (width, height) the screen (width, height) is drawn to. Integer scaling
+// falls back to fitting when the window is smaller than the screen
+pub fn viewport(window: (u32, u32), screen: (u32, u32), scaling: Scaling) -> Viewport {
+    let (window_width, window_height) = window;
+    let (screen_width, screen_height) = (screen.0.max(1), screen.1.max(1));
+    let scale = (window_width / screen_width).min(window_height / screen_height);
+    let (width, height) = if scaling == Scaling::Integer && scale > 0 {
+        (screen_width * scale, screen_height * scale)
+    } else if window_width as u64 * screen_height as u64
+        > window_height as u64 * screen_width as u64
+    {
+        // wider than the screen: bars on the sides
+        let width = window_height as u64 * screen_width as u64 / screen_height as u64;
+        (width as u32, window_height)
+    } else {
+        let height = window_width as u64 * screen_height as u64 / screen_width as u64;
+        (window_width, height as u32)
+    };
+    Viewport {
+        x: (window_width - width) / 2,
+        y: (window_height - height) / 2,
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+#[path = "./viewport_tests.rs"]
+mod viewport_tests;
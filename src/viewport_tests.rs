@@ -0,0 +1,69 @@
+use super::*;
+
+fn area(x: u32, y: u32, width: u32, height: u32) -> Viewport {
+    Viewport {
+        x,
+        y,
+        width,
+        height,
+    }
+}
+
+#[test]
+fn exact_window() {
+    for &scaling in [Scaling::Integer, Scaling::Fit].iter() {
+        assert_eq!(
+            viewport((1280, 640), (64, 32), scaling),
+            area(0, 0, 1280, 640)
+        );
+        // hires has the same aspect ratio
+        assert_eq!(
+            viewport((1280, 640), (128, 64), scaling),
+            area(0, 0, 1280, 640)
+        );
+    }
+}
+
+#[test]
+fn integer_scaling() {
+    // 15x: 960x480 centered
+    assert_eq!(
+        viewport((1000, 600), (64, 32), Scaling::Integer),
+        area(20, 60, 960, 480)
+    );
+    // smaller than the screen: fit instead
+    assert_eq!(
+        viewport((32, 32), (64, 32), Scaling::Integer),
+        area(0, 8, 32, 16)
+    );
+}
+
+#[test]
+fn fit_scaling() {
+    // bars above and below
+    assert_eq!(
+        viewport((1000, 600), (64, 32), Scaling::Fit),
+        area(0, 50, 1000, 500)
+    );
+    // bars on the sides
+    assert_eq!(
+        viewport((1000, 300), (64, 32), Scaling::Fit),
+        area(200, 0, 600, 300)
+    );
+}
+
+#[test]
+fn empty_window() {
+    assert_eq!(
+        viewport((0, 0), (64, 32), Scaling::Integer),
+        area(0, 0, 0, 0)
+    );
+}
+
+#[test]
+fn parse_scaling() {
+    assert_eq!(Scaling::parse("integer"), Some(Scaling::Integer));
+    assert_eq!(Scaling::parse("fit"), Some(Scaling::Fit));
+    assert_eq!(Scaling::parse("stretch"), None);
+    assert_eq!(Scaling::Fit.to_string(), "fit");
+}
@@ -0,0 +1,67 @@
+use crate::beep::AudioDevice;
+use crate::display::Display;
+use crate::frontend::Frontend;
+use crate::keyboard::InputDevice;
+use chip8_emulator::config::{Binding, Hotkey};
+use chip8_emulator::palette::Rgb;
+use chip8_emulator::persistence::Persistence;
+use chip8_emulator::viewport::Scaling;
+use chip8_emulator::{Chip8, Palette};
+
+// The SDL frontend: the screen in a window, the keyboard of the window and the pattern audio
+pub struct Window {
+    display: Display,
+    keyboard: InputDevice,
+    audio: AudioDevice,
+}
+
+impl Window {
+    pub fn open(
+        palette: Palette,
+        scale: u32,
+        scaling: Scaling,
+        border: Option<Rgb>,
+        persistence: Persistence,
+        bindings: &[(String, Binding)],
+    ) -> Window {
+        let display = Display::initialize(palette, scale, scaling, border, persistence);
+        let keyboard = InputDevice::new(&display.context, bindings);
+        let audio = AudioDevice::new(&display.context);
+        Window {
+            display,
+            keyboard,
+            audio,
+        }
+    }
+}
+
+impl Frontend for Window {
+    fn handle_input(&mut self, keypad: Option<&mut Chip8>) -> Vec<Hotkey> {
+        self.keyboard.handle_input(keypad)
+    }
+
+    fn draw(&mut self, chip8: &Chip8) -> Result<(), String> {
+        self.display.draw(chip8.framebuffer())
+    }
+
+    // the window was resized or uncovered, or the last frames are fading out
+    fn needs_draw(&mut self) -> bool {
+        self.keyboard.take_redraw() || self.display.fading()
+    }
+
+    fn beep(&mut self, chip8: &Chip8) {
+        self.audio.beep(chip8.audio_pattern(), chip8.pitch());
+    }
+
+    fn stop_beep(&mut self) {
+        self.audio.stop_beep();
+    }
+
+    fn set_palette(&mut self, palette: Palette) {
+        self.display.palette = palette;
+    }
+
+    fn toggle_fullscreen(&mut self) -> Result<(), String> {
+        self.display.toggle_fullscreen()
+    }
+}
@@ -0,0 +1,82 @@
+// Whole programs against golden images: each ROM runs headless for a fixed number of frames and
+// its screen is compared with tests/goldens/<name>.txt (the ASCII art of headless::ascii).
+// These are the games of Roms/, on the default profile: they cover the common instructions, not
+// the quirks of each interpreter, which the unit tests of the opcodes check.
+//
+// After a deliberate change of behaviour, regenerate the goldens and review their diff:
+//     UPDATE_GOLDENS=1 cargo test --test golden
+use chip8_emulator::headless::{self, Stop};
+use chip8_emulator::{Chip8, Mode, Quirks};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const ROOT: &str = env!("CARGO_MANIFEST_DIR");
+// the frames the games run, long enough to get past the setup of all of them
+const GAME_FRAMES: usize = 300;
+
+// Run a ROM, then compare its screen with its golden, or write the golden when updating
+fn check(rom: &Path, golden: &Path) -> Result<(), String> {
+    let mut chip8 = Chip8::new_with_seed(Quirks::default(), 0);
+    chip8
+        .load_rom(rom.to_str().unwrap())
+        .map_err(|e| e.to_string())?;
+    let ipf = Mode::Chip8.default_ipf();
+    let outcome = headless::run(&mut chip8, ipf, GAME_FRAMES, None, &[]);
+    if let Stop::Error(e) = outcome.stop {
+        return Err(format!("execution error: {}", e));
+    }
+    let screen = headless::ascii(chip8.framebuffer());
+
+    if env::var_os("UPDATE_GOLDENS").is_some() {
+        fs::create_dir_all(golden.parent().unwrap()).map_err(|e| e.to_string())?;
+        return fs::write(golden, screen).map_err(|e| e.to_string());
+    }
+    let expected = fs::read_to_string(golden)
+        .map_err(|_| "no golden, run with UPDATE_GOLDENS=1 to create it".to_string())?;
+    if screen == expected {
+        return Ok(());
+    }
+    let row = screen
+        .lines()
+        .zip(expected.lines())
+        .position(|(actual, expected)| actual != expected)
+        .unwrap_or(0);
+    Err(format!(
+        "the screen differs from the golden from row {}:\n{}",
+        row, screen
+    ))
+}
+
+fn golden(name: &str) -> PathBuf {
+    Path::new(ROOT)
+        .join("tests/goldens")
+        .join(name)
+        .with_extension("txt")
+}
+
+fn report(failures: Vec<String>) {
+    assert!(
+        failures.is_empty(),
+        "{} golden image failures:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn games() {
+    let mut roms: Vec<PathBuf> = fs::read_dir(Path::new(ROOT).join("Roms"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    roms.sort();
+    let mut failures = Vec::new();
+    for rom in roms {
+        let name = rom.file_name().unwrap().to_str().unwrap().to_string();
+        if let Err(e) = check(&rom, &golden(&name)) {
+            failures.push(format!("{}: {}", name, e));
+        }
+    }
+    report(failures);
+}